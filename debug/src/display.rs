@@ -0,0 +1,300 @@
+use quote::{format_ident, quote};
+use syn::{ext::IdentExt, parse_quote};
+
+use crate::{add_inferred_bounds, get_attr_items, parse_bound_predicates, parse_placeholders};
+
+pub(crate) fn generate_display_trait(st: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let container_attrs = get_container_display_attrs(st)?;
+    let struct_name_ident = &st.ident;
+
+    // 结构体和枚举的每个分支都先解析成 (匹配模式, 模板) 的形式，之后统一生成`match`表达式
+    let mut arms = Vec::new();
+    let mut referenced_fields = Vec::new();
+    match st.data {
+        syn::Data::Struct(ref data) => {
+            reject_field_display_attrs(&data.fields)?;
+            let template = container_attrs.template.ok_or_else(|| {
+                syn::Error::new_spanned(struct_name_ident, r#"missing `#[display("...")]` template"#)
+            })?;
+            let path = quote!(Self);
+            arms.push(generate_match_arm(&path, &data.fields, &template, &mut referenced_fields)?);
+        }
+        syn::Data::Enum(ref data) => {
+            if let Some(template) = container_attrs.template {
+                return Err(syn::Error::new_spanned(
+                    template,
+                    r#"put a `#[display("...")]` template on each variant of an enum"#,
+                ));
+            }
+            for variant in &data.variants {
+                reject_field_display_attrs(&variant.fields)?;
+                let variant_ident = &variant.ident;
+                let path = quote!(Self::#variant_ident);
+                match get_variant_display_template(variant)? {
+                    Some(template) => {
+                        arms.push(generate_match_arm(&path, &variant.fields, &template, &mut referenced_fields)?);
+                    }
+                    // 没有字段的分支默认输出分支的名字
+                    None if variant.fields.is_empty() => {
+                        let variant_name_str = variant_ident.to_string();
+                        let pattern = match variant.fields {
+                            syn::Fields::Named(_) => quote!(#path {}),
+                            syn::Fields::Unnamed(_) => quote!(#path()),
+                            syn::Fields::Unit => quote!(#path),
+                        };
+                        arms.push(quote!(#pattern => __formatter.write_str(#variant_name_str)));
+                    }
+                    None => {
+                        return Err(syn::Error::new_spanned(
+                            variant,
+                            r#"missing `#[display("...")]` template for this variant"#,
+                        ));
+                    }
+                }
+            }
+        }
+        syn::Data::Union(_) => {
+            return Err(syn::Error::new_spanned(st, "CustomDisplay cannot be derived for unions"));
+        }
+    }
+
+    let mut generics_param_to_modify = st.generics.clone();
+    if let Some(hatch) = container_attrs.bound {
+        generics_param_to_modify.make_where_clause().predicates.extend(hatch);
+    } else {
+        // 字段按照模板中占位符要求的Trait分组，每组分别推断限定条件，例如`{id:#x}`要求`LowerHex`
+        let mut groups: Vec<(syn::Path, Vec<&syn::Field>)> = Vec::new();
+        for (field, bound) in referenced_fields {
+            match groups.iter_mut().find(|(b, _)| *b == bound) {
+                Some((_, fields)) => fields.push(field),
+                None => groups.push((bound, vec![field])),
+            }
+        }
+        for (bound, fields) in groups {
+            add_inferred_bounds(st, &mut generics_param_to_modify, &fields, &bound);
+        }
+    }
+    let (impl_generics, type_generics, where_clause) = generics_param_to_modify.split_for_impl();
+
+    let ret_stream = quote! {
+        impl #impl_generics std::fmt::Display for #struct_name_ident #type_generics #where_clause {
+            fn fmt(&self, __formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                match *self {
+                    #(#arms,)*
+                }
+            }
+        }
+    };
+
+    Ok(ret_stream)
+}
+
+// 生成形如`Self::Variant { name, id, .. } => write!(__formatter, "{name} ({id:#x})", name = name, id = id)`的分支，
+// 同时把模板中用到的字段以及它们需要的Trait记录到`referenced_fields`中
+fn generate_match_arm<'a>(
+    path: &proc_macro2::TokenStream,
+    fields: &'a syn::Fields,
+    template: &syn::LitStr,
+    referenced_fields: &mut Vec<(&'a syn::Field, syn::Path)>,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let value = template.value();
+    let placeholders = parse_placeholders(&value).map_err(|e| syn::Error::new_spanned(template, e))?;
+
+    // 模板中的字段引用都会被替换成绑定变量的名字，元组字段`{0}`替换成`{_0}`
+    let mut rewritten = String::new();
+    let mut last = 0;
+    let mut bindings: Vec<(usize, syn::Ident)> = Vec::new();
+    let mut count_indices = Vec::new();
+    for placeholder in placeholders {
+        let arg = &value[placeholder.arg.clone()];
+        let (index, binding) = resolve_template_arg(arg, fields, template)?;
+        rewritten.push_str(&value[last..placeholder.arg.start]);
+        rewritten.push_str(&binding.to_string());
+        last = placeholder.arg.end;
+
+        let spec = placeholder.spec.as_ref().map(|spec| &value[spec.clone()]).unwrap_or("");
+        referenced_fields.push((fields.iter().nth(index).unwrap(), get_format_trait(spec)));
+        if !bindings.iter().any(|(i, _)| *i == index) {
+            bindings.push((index, binding));
+        }
+
+        // 宽度和精度可以引用其他字段，例如`{name:>width$}`，同样替换成绑定变量的名字
+        let counts = match placeholder.spec {
+            Some(spec) => get_count_args(&value, spec).map_err(|e| syn::Error::new_spanned(template, e))?,
+            None => Vec::new(),
+        };
+        for count in counts {
+            let (index, binding) = resolve_template_arg(&value[count.clone()], fields, template)?;
+            rewritten.push_str(&value[last..count.start]);
+            rewritten.push_str(&binding.to_string());
+            last = count.end;
+
+            count_indices.push(index);
+            if !bindings.iter().any(|(i, _)| *i == index) {
+                bindings.push((index, binding));
+            }
+        }
+    }
+    rewritten.push_str(&value[last..]);
+    let rewritten = syn::LitStr::new(&rewritten, template.span());
+
+    let pattern = match fields {
+        syn::Fields::Named(_) => {
+            let members = bindings.iter().map(|(index, _)| fields.iter().nth(*index).unwrap().ident.as_ref().unwrap());
+            let bindings = bindings.iter().map(|(_, binding)| binding);
+            quote!(#path { #(#members: ref #bindings,)* .. })
+        }
+        syn::Fields::Unnamed(_) => {
+            let elems = (0..fields.len()).map(|i| match bindings.iter().find(|(index, _)| *index == i) {
+                Some((_, binding)) => quote!(ref #binding),
+                None => quote!(_),
+            });
+            quote!(#path(#(#elems),*))
+        }
+        syn::Fields::Unit => quote!(#path),
+    };
+    // 字段按引用绑定，而宽度和精度要求的是`usize`的值，需要解引用
+    let args = bindings.iter().map(|(index, binding)| {
+        if count_indices.contains(index) {
+            quote!(#binding = *#binding)
+        } else {
+            quote!(#binding = #binding)
+        }
+    });
+    Ok(quote!(#pattern => write!(__formatter, #rewritten #(, #args)*)))
+}
+
+// 找出格式说明中`width$`、`.prec$`这样引用参数的宽度和精度，返回参数名在格式串中的位置。
+// `.*`需要一个额外的位置参数，模板中没有这样的参数
+fn get_count_args(format: &str, spec: std::ops::Range<usize>) -> Result<Vec<std::ops::Range<usize>>, String> {
+    // 跳过填充字符和对齐方式，填充字符本身也可能是`$`
+    let mut start = spec.start;
+    let mut chars = format[spec.clone()].chars();
+    if let (Some(fill), Some('<' | '^' | '>')) = (chars.next(), chars.next()) {
+        start += fill.len_utf8() + 1;
+    }
+    let text = &format[start..spec.end];
+    if text.contains(".*") {
+        return Err("precision `.*` is not supported in a display template, name the field holding the precision like `{value:.prec$}`".to_string());
+    }
+    let mut counts = Vec::new();
+    for (pos, _) in text.match_indices('$') {
+        let before = &text[..pos];
+        let mut arg_start = before.trim_end_matches(|c: char| c.is_alphanumeric() || c == '_').len();
+        // `{:0width$}`中的`0`是补零的标记，不是参数名的一部分
+        if before[arg_start..].len() > 1 && before[arg_start..].starts_with('0') {
+            arg_start += 1;
+        }
+        if arg_start == pos {
+            return Err(format!("expected a field name before `$` in the format spec `{}`", &format[spec.clone()]));
+        }
+        counts.push(start + arg_start..start + pos);
+    }
+    Ok(counts)
+}
+
+// 把模板占位符中的参数解析为字段的下标以及绑定变量的名字
+fn resolve_template_arg(arg: &str, fields: &syn::Fields, template: &syn::LitStr) -> syn::Result<(usize, syn::Ident)> {
+    if arg.is_empty() {
+        return Err(syn::Error::new_spanned(
+            template,
+            "positional `{}` placeholders are not supported in a display template, name the field like `{name}` or `{0}`",
+        ));
+    }
+    let found = match fields {
+        // 原始标识符`r#type`在模板中写作`{type}`，关键字不能作为`write!`的参数名，换一个名字绑定
+        syn::Fields::Named(_) => fields
+            .iter()
+            .position(|f| f.ident.as_ref().unwrap().unraw() == arg)
+            .map(|index| match fields.iter().nth(index).unwrap().ident {
+                Some(ref ident) if *ident != arg => (index, format_ident!("__raw_{}", arg)),
+                _ => (index, format_ident!("{}", arg)),
+            }),
+        syn::Fields::Unnamed(_) => arg
+            .parse::<usize>()
+            .ok()
+            .filter(|index| *index < fields.len())
+            .map(|index| (index, format_ident!("_{}", index))),
+        syn::Fields::Unit => None,
+    };
+    found.ok_or_else(|| syn::Error::new_spanned(template, format!("no field named `{}` to use in the display template", arg)))
+}
+
+// 根据占位符的格式说明确定字段需要实现的格式化Trait
+pub(crate) fn get_format_trait(spec: &str) -> syn::Path {
+    if spec.ends_with('?') {
+        return parse_quote!(std::fmt::Debug);
+    }
+    match spec.chars().last() {
+        Some('x') => parse_quote!(std::fmt::LowerHex),
+        Some('X') => parse_quote!(std::fmt::UpperHex),
+        Some('o') => parse_quote!(std::fmt::Octal),
+        Some('b') => parse_quote!(std::fmt::Binary),
+        Some('e') => parse_quote!(std::fmt::LowerExp),
+        Some('E') => parse_quote!(std::fmt::UpperExp),
+        Some('p') => parse_quote!(std::fmt::Pointer),
+        _ => parse_quote!(std::fmt::Display),
+    }
+}
+
+// 结构体或枚举上`#[display(...)]`形式的属性的解析结果
+#[derive(Default)]
+struct ContainerDisplayAttrs {
+    template: Option<syn::LitStr>,
+    bound: Option<Vec<syn::WherePredicate>>,
+}
+
+fn get_container_display_attrs(st: &syn::DeriveInput) -> syn::Result<ContainerDisplayAttrs> {
+    let mut ret = ContainerDisplayAttrs::default();
+    for item in get_attr_items(&st.attrs, "display")? {
+        match item {
+            syn::NestedMeta::Lit(syn::Lit::Str(ref lit)) => {
+                if ret.template.is_some() {
+                    return Err(syn::Error::new_spanned(lit, "duplicate display template"));
+                }
+                ret.template = Some(lit.clone());
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                ref path,
+                lit: syn::Lit::Str(ref lit),
+                ..
+            })) if path.is_ident("bound") => {
+                if ret.bound.is_some() {
+                    return Err(syn::Error::new_spanned(path, "duplicate `bound` attribute"));
+                }
+                ret.bound = Some(parse_bound_predicates(lit)?);
+            }
+            other => {
+                return Err(syn::Error::new_spanned(other, r#"expected a template string or `bound = "..."`"#));
+            }
+        }
+    }
+    Ok(ret)
+}
+
+fn get_variant_display_template(variant: &syn::Variant) -> syn::Result<Option<syn::LitStr>> {
+    let mut template = None;
+    for item in get_attr_items(&variant.attrs, "display")? {
+        match item {
+            syn::NestedMeta::Lit(syn::Lit::Str(ref lit)) if template.is_none() => {
+                template = Some(lit.clone());
+            }
+            other => {
+                return Err(syn::Error::new_spanned(other, "expected a single template string"));
+            }
+        }
+    }
+    Ok(template)
+}
+
+fn reject_field_display_attrs(fields: &syn::Fields) -> syn::Result<()> {
+    for field in fields {
+        if let Some(attr) = field.attrs.iter().find(|attr| attr.path.is_ident("display")) {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "`#[display(...)]` is not supported on fields, reference the field from the template instead",
+            ));
+        }
+    }
+    Ok(())
+}
@@ -1,8 +1,10 @@
-use std::collections::HashMap;
+use std::collections::HashSet;
 
 use proc_macro::TokenStream;
-use syn::{self, parse_quote, visit::{self, Visit}};
-use quote::{quote};
+use syn::{self, ext::IdentExt, parse_quote, spanned::Spanned, visit::{self, Visit}};
+use quote::{format_ident, quote, quote_spanned};
+
+mod display;
 
 #[proc_macro_derive(CustomDebug, attributes(debug))]
 pub fn derive(input: TokenStream) -> TokenStream {
@@ -13,103 +15,610 @@ pub fn derive(input: TokenStream) -> TokenStream {
     }
 }
 
+#[proc_macro_derive(CustomDisplay, attributes(display))]
+pub fn derive_display(input: TokenStream) -> TokenStream {
+    let input_derive = syn::parse_macro_input!(input as syn::DeriveInput);
+    match display::generate_display_trait(&input_derive) {
+        Ok(token_stream) => token_stream.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
 fn do_expand(st: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     let ret = generate_debug_trait(st)?;
 
     Ok(ret)
 }
 
-type StructFields = syn::punctuated::Punctuated<syn::Field, syn::Token!(,)>;
-
-fn get_fields_from_derive_input(d: &syn::DeriveInput) -> syn::Result<&StructFields> {
-    if let syn::Data::Struct(syn::DataStruct {
-        fields: syn::Fields::Named(syn::FieldsNamed { ref named, .. }),
-        ..
-    }) = d.data {
-        return Ok(named);
+// 结构体的字段或者枚举每个分支的字段，附带分支上的属性（结构体没有分支属性，使用默认值）
+fn get_field_groups(d: &syn::DeriveInput) -> syn::Result<Vec<(VariantDebugAttrs, &syn::Fields)>> {
+    match d.data {
+        syn::Data::Struct(ref data) => Ok(vec![(VariantDebugAttrs::default(), &data.fields)]),
+        syn::Data::Enum(ref data) => data
+            .variants
+            .iter()
+            .map(|variant| Ok((get_variant_debug_attrs(variant)?, &variant.fields)))
+            .collect(),
+        syn::Data::Union(_) => Ok(Vec::new()),
     }
-    Err(syn::Error::new_spanned(d, "Must define on a Struct, not Enum".to_string()))
 }
 
-fn generate_debug_trait_core(st: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
-    let fields = get_fields_from_derive_input(st)?;
+fn generate_debug_trait_core(st: &syn::DeriveInput, container_attrs: &ContainerDebugAttrs) -> syn::Result<proc_macro2::TokenStream> {
     let struct_name_ident = &st.ident;
-    let struct_name_str = struct_name_ident.to_string();
-    let mut fmt_body_stream = proc_macro2::TokenStream::new();
-
-    fmt_body_stream.extend(quote!(
-        fmt.debug_struct(#struct_name_str)
-    ));
-    for field in fields.iter() {
-        let field_name_ident = field.ident.as_ref().unwrap();
-        let field_name_str = field_name_ident.to_string();
-        
-        let mut format_str = "{:?}".to_string();
-        if let Some(format) = get_custom_format_of_field(field)? {
-            format_str = format;
-        }
-
-        fmt_body_stream.extend(quote!(
-            .field(#field_name_str, &format_args!(#format_str, self.#field_name_ident))
+    // logfmt只输出键值对，没有地方放枚举分支的名字，元组字段也没有键
+    if let DebugStyle::Logfmt = container_attrs.style {
+        if !matches!(st.data, syn::Data::Struct(syn::DataStruct { fields: syn::Fields::Named(_), .. })) {
+            return Err(syn::Error::new_spanned(
+                struct_name_ident,
+                r#"`style = "logfmt"` is only supported on structs with named fields"#,
+            ));
+        }
+    }
+    // 计算值需要有名字，只能追加到`debug_struct`的输出中
+    if !container_attrs.computed_fields.is_empty()
+        && !matches!(st.data, syn::Data::Struct(syn::DataStruct { fields: syn::Fields::Named(_), .. }))
+    {
+        return Err(syn::Error::new_spanned(
+            struct_name_ident,
+            "`field(...)` is only supported on structs with named fields",
         ));
     }
+    let mut uses_custom_formatter = false;
+    let mut fmt_body_stream = match st.data {
+        syn::Data::Struct(ref data) => {
+            let struct_name_str = container_attrs.rename.clone().unwrap_or_else(|| struct_name_ident.to_string());
+            let packed = is_packed_struct(st);
+            let mut packed_copies = Vec::new();
+            let fmt_body_stream = generate_fields_debug(
+                &struct_name_str,
+                &data.fields,
+                container_attrs,
+                container_attrs.rename_all,
+                &mut |idx, field| {
+                    let member = match field.ident {
+                        Some(ref ident) => quote!(#ident),
+                        None => {
+                            let index = syn::Index::from(idx);
+                            quote!(#index)
+                        }
+                    };
+                    // 紧凑（packed）结构体的字段可能没有对齐，不能直接引用，需要先复制到局部变量中再格式化
+                    if !packed {
+                        return quote!(self.#member);
+                    }
+                    let local = match field.ident {
+                        Some(ref ident) => format_ident!("__packed_{}", ident.unraw()),
+                        None => format_ident!("__packed_{}", idx),
+                    };
+                    // 报错位置指向字段的类型，方便用户看出是哪个字段不满足`Copy`
+                    let copy_fn = quote_spanned!(field.ty.span()=> __copy_packed_field);
+                    packed_copies.push(quote!(let #local = #copy_fn(self.#member);));
+                    quote!(#local)
+                },
+                &mut uses_custom_formatter,
+            )?;
+            if packed_copies.is_empty() {
+                fmt_body_stream
+            } else {
+                quote!(
+                    #[diagnostic::on_unimplemented(
+                        message = "`{Self}` must be `Copy` to be formatted in a `#[repr(packed)]` struct",
+                        note = "fields of a packed struct may be unaligned and cannot be borrowed, so CustomDebug copies each field before formatting it",
+                        note = "use `#[debug(skip)]`, `#[debug(redact)]` or a container level `#[debug(with = \"...\")]` for fields that are not `Copy`"
+                    )]
+                    trait __PackedFieldMustBeCopy: Copy {}
+                    impl<__T: Copy> __PackedFieldMustBeCopy for __T {}
+                    fn __copy_packed_field<__T: __PackedFieldMustBeCopy>(value: __T) -> __T {
+                        value
+                    }
+                    #(#packed_copies)*
+                    #fmt_body_stream
+                )
+            }
+        }
+        syn::Data::Enum(ref data) => {
+            // 枚举的输出以分支的名字开头，不会用到枚举本身的名字
+            if let Some(ref rename) = container_attrs.rename {
+                return Err(syn::Error::new_spanned(
+                    struct_name_ident,
+                    format!("`rename = {:?}` has no effect on an enum, rename the variants instead", rename),
+                ));
+            }
+            let mut arms = Vec::new();
+            for variant in &data.variants {
+                let variant_attrs = get_variant_debug_attrs(variant)?;
+                let variant_ident = &variant.ident;
+                // 和serde一样，枚举上的`rename_all`作用在分支的名字上，分支中的字段名保持不变
+                let variant_name_str = variant_attrs.rename.unwrap_or_else(|| {
+                    let name = variant_ident.unraw().to_string();
+                    match container_attrs.rename_all {
+                        Some(rule) => rule.apply_to_variant(&name),
+                        None => name,
+                    }
+                });
+
+                // 被跳过的分支只输出分支的名字，有字段的分支用`..`提示内容被省略了
+                if variant_attrs.skip {
+                    let fmt_body_stream = match variant.fields {
+                        syn::Fields::Named(_) => quote!(fmt.debug_struct(#variant_name_str).finish_non_exhaustive()),
+                        syn::Fields::Unnamed(_) => quote!(fmt.debug_tuple(#variant_name_str).finish_non_exhaustive()),
+                        syn::Fields::Unit => quote!(fmt.write_str(#variant_name_str)),
+                    };
+                    arms.push(quote!(Self::#variant_ident { .. } => { #fmt_body_stream }));
+                    continue;
+                }
+
+                // 每个字段都按引用绑定到`__self_0`、`__self_1`这样的变量上
+                let bindings: Vec<_> = (0..variant.fields.len()).map(|idx| format_ident!("__self_{}", idx)).collect();
+                let pattern = match variant.fields {
+                    syn::Fields::Named(ref named) => {
+                        let members = named.named.iter().map(|f| f.ident.as_ref().unwrap());
+                        quote!(Self::#variant_ident { #(#members: ref #bindings),* })
+                    }
+                    syn::Fields::Unnamed(_) => quote!(Self::#variant_ident(#(ref #bindings),*)),
+                    syn::Fields::Unit => quote!(Self::#variant_ident),
+                };
+                let fmt_body_stream = generate_fields_debug(
+                    &variant_name_str,
+                    &variant.fields,
+                    container_attrs,
+                    None,
+                    &mut |idx, _| {
+                        let binding = &bindings[idx];
+                        quote!((*#binding))
+                    },
+                    &mut uses_custom_formatter,
+                )?;
+                arms.push(quote!(#pattern => { #fmt_body_stream }));
+            }
+            quote!(
+                match *self {
+                    #(#arms)*
+                }
+            )
+        }
+        syn::Data::Union(_) => unreachable!("unions are rejected by `generate_debug_trait`"),
+    };
+
+    // 紧凑模式忽略`{:#?}`，用单行的形式重新格式化，嵌套的值也就都是单行输出了
+    if let DebugStyle::Compact = container_attrs.style {
+        fmt_body_stream = quote!(
+            if fmt.alternate() {
+                return write!(fmt, "{:?}", self);
+            }
+            #fmt_body_stream
+        );
+    }
 
-    fmt_body_stream.extend(quote!(.finish()));
-    return Ok(fmt_body_stream);
+    if uses_custom_formatter {
+        fmt_body_stream = with_custom_formatter_wrapper(fmt_body_stream);
+    }
+    Ok(fmt_body_stream)
 }
 
-fn generate_debug_trait(st: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+// 在格式化代码前面定义`__DebugWith`包装类型，自定义格式化函数通过它接入`debug_struct`等
+fn with_custom_formatter_wrapper(fmt_body_stream: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    quote!(
+        struct __DebugWith<'__a, __T: ?Sized>(
+            &'__a __T,
+            fn(&__T, &mut std::fmt::Formatter) -> std::fmt::Result,
+        );
+        impl<'__a, __T: ?Sized> std::fmt::Debug for __DebugWith<'__a, __T> {
+            fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+                (self.1)(self.0, fmt)
+            }
+        }
+        #fmt_body_stream
+    )
+}
+
+// 生成一组字段（结构体本身或者枚举的一个分支）的格式化代码，`access`给出读取第几个字段的表达式，
+// `rename_rule`是字段名要遵循的命名风格
+fn generate_fields_debug(
+    name_str: &str,
+    fields: &syn::Fields,
+    container_attrs: &ContainerDebugAttrs,
+    rename_rule: Option<RenameRule>,
+    access: &mut dyn FnMut(usize, &syn::Field) -> proc_macro2::TokenStream,
+    uses_custom_formatter: &mut bool,
+) -> syn::Result<proc_macro2::TokenStream> {
+    // 先算出每个字段输出时用的名字和值，再按照`style`组装成最终的输出
+    let mut field_names = Vec::new();
+    let mut field_values = Vec::new();
+    let mut has_skipped_field = false;
+    for (idx, field) in fields.iter().enumerate() {
+        let mut field_attrs = get_field_debug_attrs(field)?;
+        // 字段上的`rename`优先于结构体上的`rename_all`，原始标识符`r#type`输出为`type`
+        let field_name_str = match (field_attrs.rename.take(), &field.ident) {
+            (Some(_), None) => {
+                return Err(syn::Error::new_spanned(field, "`rename` is only supported on named fields"));
+            }
+            (Some(rename), Some(_)) => rename,
+            (None, Some(ident)) => {
+                let name = ident.unraw().to_string();
+                match rename_rule {
+                    Some(rule) => rule.apply_to_field(&name),
+                    None => name,
+                }
+            }
+            (None, None) => idx.to_string(),
+        };
+
+        if field_attrs.skip {
+            has_skipped_field = true;
+            continue;
+        }
+        // 标记字段不携带任何数据，省略时也不需要用`..`提示
+        if container_attrs.skip_markers && !field_attrs.show && is_marker_type(&field.ty) {
+            continue;
+        }
+        field_names.push(field_name_str);
+
+        field_values.push(generate_field_debug_value(field, field_attrs, || access(idx, field), uses_custom_formatter));
+    }
+
+    // 容器上`field(...)`给出的计算值追加在所有字段之后
+    if let syn::Fields::Named(_) = fields {
+        for (name, expr) in &container_attrs.computed_fields {
+            field_names.push(name.clone());
+            field_values.push(quote!(&(#expr)));
+        }
+    }
+
+    // 有字段被省略时，用`..`提示读者输出并不完整
+    let finish = if has_skipped_field {
+        quote!(finish_non_exhaustive)
+    } else {
+        quote!(finish)
+    };
+    let fmt_body_stream = match (container_attrs.style, fields) {
+        (_, syn::Fields::Unit) => quote!(fmt.write_str(#name_str)),
+        (DebugStyle::Struct | DebugStyle::Compact, syn::Fields::Unnamed(_)) => quote!(
+            fmt.debug_tuple(#name_str)
+                #(.field(#field_values))*
+                .#finish()
+        ),
+        (DebugStyle::Struct | DebugStyle::Compact, syn::Fields::Named(_)) => quote!(
+            fmt.debug_struct(#name_str)
+                #(.field(#field_names, #field_values))*
+                .#finish()
+        ),
+        (DebugStyle::Logfmt, _) => {
+            // 输出形如`name=F bitmask=0b00011100`的键值对，需要时给值加上引号
+            let prefixes = field_names.iter().enumerate().map(|(idx, name)| {
+                if idx == 0 {
+                    format!("{}=", name)
+                } else {
+                    format!(" {}=", name)
+                }
+            });
+            quote!(
+                fn __logfmt_value(fmt: &mut std::fmt::Formatter, value: &dyn std::fmt::Debug) -> std::fmt::Result {
+                    let value = format!("{:?}", value);
+                    let needs_quotes = |s: &str| {
+                        s.is_empty() || s.chars().any(|c| c.is_whitespace() || c.is_control() || c == '=' || c == '"')
+                    };
+                    // 字符串的调试输出（例如`"F"`）本身就是带引号的合法值，内容不需要引号时再把引号去掉
+                    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+                        let inner = &value[1..value.len() - 1];
+                        if !needs_quotes(inner) && !inner.contains('\\') {
+                            return fmt.write_str(inner);
+                        }
+                        return fmt.write_str(&value);
+                    }
+                    if needs_quotes(&value) {
+                        write!(fmt, "{:?}", value)
+                    } else {
+                        fmt.write_str(&value)
+                    }
+                }
+                #(
+                    fmt.write_str(#prefixes)?;
+                    __logfmt_value(fmt, #field_values)?;
+                )*
+                Ok(())
+            )
+        }
+    };
+    Ok(fmt_body_stream)
+}
+
+// 生成一个字段传给`.field(...)`的值，按照字段上的`redact`、`expr`、`with`、`len_only`、`limit`和自定义格式处理。
+// `access`给出读取字段的表达式，只有真正需要读取字段时才会调用它
+fn generate_field_debug_value(
+    field: &syn::Field,
+    field_attrs: FieldDebugAttrs,
+    access: impl FnOnce() -> proc_macro2::TokenStream,
+    uses_custom_formatter: &mut bool,
+) -> proc_macro2::TokenStream {
+    // 脱敏字段只输出占位符，不会去访问字段本身的值
+    if let Some(placeholder) = field_attrs.redact {
+        return quote!(&format_args!("{}", #placeholder));
+    }
+
+    // 用表达式的值代替字段的值，表达式中可以通过`self`访问整个结构体
+    if let Some(expr) = field_attrs.expr {
+        return generate_field_value(quote!((#expr)), &field_attrs.format, &field_attrs.alt, uses_custom_formatter);
+    }
+
+    let field_access = access();
+
+    // 用户自定义的格式化函数通过包装类型接入`debug_struct`
+    if let Some(with) = field_attrs.with {
+        *uses_custom_formatter = true;
+        return quote!(&__DebugWith(&#field_access, #with));
+    }
+
+    // 只输出集合的长度，不需要元素实现`Debug`
+    if field_attrs.len_only {
+        return quote!(&format_args!("<{} items>", #field_access.len()));
+    }
+
+    // 只输出集合的前`limit`个元素，自定义格式作用在每个元素上（映射类型作用在值上）
+    if let Some(limit) = field_attrs.limit {
+        *uses_custom_formatter = true;
+        let collection = if is_map_type(&field.ty) {
+            let value = generate_field_value(quote!(value), &field_attrs.format, &field_attrs.alt, uses_custom_formatter);
+            quote!(
+                let mut collection = fmt.debug_set();
+                for (key, value) in iter.by_ref().take(#limit) {
+                    collection.entry(&format_args!("{:?}: {:?}", key, #value));
+                }
+            )
+        } else {
+            let item = generate_field_value(quote!(item), &field_attrs.format, &field_attrs.alt, uses_custom_formatter);
+            quote!(
+                let mut collection = fmt.debug_list();
+                for item in iter.by_ref().take(#limit) {
+                    collection.entry(#item);
+                }
+            )
+        };
+        return quote!(&__DebugWith(&#field_access, |value, fmt| {
+            let mut iter = value.into_iter();
+            #collection
+            let rest = iter.count();
+            if rest > 0 {
+                collection.entry(&format_args!("... ({} more)", rest));
+            }
+            collection.finish()
+        }));
+    }
+
+    generate_field_value(field_access, &field_attrs.format, &field_attrs.alt, uses_custom_formatter)
+}
+
+// 按照自定义格式生成传给`.field(...)`的值
+fn generate_field_value(
+    value: proc_macro2::TokenStream,
+    format: &Option<syn::LitStr>,
+    alt: &Option<syn::LitStr>,
+    uses_custom_formatter: &mut bool,
+) -> proc_macro2::TokenStream {
+    // 没有自定义格式的字段直接交给`debug_struct`，这样外层`Formatter`上的`{:#?}`等设置会原样传递下去
+    if format.is_none() && alt.is_none() {
+        return quote!(&#value);
+    }
 
-    let fmt_body_stream = generate_debug_trait_core(st)?;
+    // `format_args!`会丢掉外层`Formatter`的设置，所以在运行时根据`alternate()`选择对应的格式串
+    let format_str = format.as_ref().map(|f| f.value()).unwrap_or_else(|| "{:?}".to_string());
+    let alt_format_str = match alt {
+        Some(alt) => alt.value(),
+        None => make_alternate_format(&format_str),
+    };
+    if alt_format_str == format_str {
+        return quote!(&format_args!(#format_str, #value));
+    }
+    *uses_custom_formatter = true;
+    quote!(&__DebugWith(&#value, |value, fmt| {
+        if fmt.alternate() {
+            write!(fmt, #alt_format_str, value)
+        } else {
+            write!(fmt, #format_str, value)
+        }
+    }))
+}
+
+// 通过类型名判断字段是否为映射类型，映射类型按`{key: value, ..}`的形式输出
+fn is_map_type(ty: &syn::Type) -> bool {
+    if let syn::Type::Path(syn::TypePath { ref path, .. }) = ty {
+        if let Some(seg) = path.segments.last() {
+            return seg.ident == "HashMap" || seg.ident == "BTreeMap" || seg.ident == "IndexMap";
+        }
+    }
+    false
+}
+
+// 判断结构体是否带有`#[repr(packed)]`或者`#[repr(C, packed(N))]`之类的属性
+fn is_packed_struct(st: &syn::DeriveInput) -> bool {
+    st.attrs.iter().any(|attr| {
+        if let Ok(syn::Meta::List(syn::MetaList { ref path, ref nested, .. })) = attr.parse_meta() {
+            if path.is_ident("repr") {
+                return nested.iter().any(|item| match item {
+                    syn::NestedMeta::Meta(syn::Meta::Path(ref path)) => path.is_ident("packed"),
+                    syn::NestedMeta::Meta(syn::Meta::List(syn::MetaList { ref path, .. })) => path.is_ident("packed"),
+                    _ => false,
+                });
+            }
+        }
+        false
+    })
+}
+
+// 判断字段是否为不携带数据的标记类型：`PhantomData`、`PhantomPinned`、`()`以及`[T; 0]`
+fn is_marker_type(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(syn::TypePath { ref path, .. }) => path
+            .segments
+            .last()
+            .is_some_and(|seg| seg.ident == "PhantomData" || seg.ident == "PhantomPinned"),
+        syn::Type::Tuple(syn::TypeTuple { ref elems, .. }) => elems.is_empty(),
+        syn::Type::Array(syn::TypeArray {
+            len: syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Int(ref len), .. }),
+            ..
+        }) => len.base10_digits() == "0",
+        syn::Type::Paren(syn::TypeParen { ref elem, .. }) | syn::Type::Group(syn::TypeGroup { ref elem, .. }) => {
+            is_marker_type(elem)
+        }
+        _ => false,
+    }
+}
+
+// 找出`#[debug(transparent)]`要转发的字段：结构体中唯一一个不是标记类型的字段，同时返回它的下标
+fn get_transparent_field(st: &syn::DeriveInput) -> syn::Result<(usize, &syn::Field)> {
+    let fields = match st.data {
+        syn::Data::Struct(ref data) => &data.fields,
+        _ => return Err(syn::Error::new_spanned(&st.ident, "`transparent` is only supported on structs")),
+    };
+    let mut candidates = fields.iter().enumerate().filter(|(_, field)| !is_marker_type(&field.ty));
+    match (candidates.next(), candidates.next()) {
+        (Some(found), None) => Ok(found),
+        (Some(_), Some((_, extra))) => Err(syn::Error::new_spanned(
+            extra,
+            "`transparent` requires exactly one field that is not zero-sized, found a second one here",
+        )),
+        (None, _) => Err(syn::Error::new_spanned(
+            &st.ident,
+            "`transparent` requires a field that is not zero-sized to forward to",
+        )),
+    }
+}
+
+// 被转发的字段上的属性，输出中没有字段本身，跳过它或者给它改名都没有意义
+fn get_transparent_field_attrs(field: &syn::Field) -> syn::Result<FieldDebugAttrs> {
+    let field_attrs = get_field_debug_attrs(field)?;
+    if field_attrs.skip {
+        return Err(syn::Error::new_spanned(field, "the field a `transparent` type forwards to cannot be skipped"));
+    }
+    if field_attrs.rename.is_some() {
+        return Err(syn::Error::new_spanned(field, "`rename` has no effect on the field a `transparent` type forwards to"));
+    }
+    Ok(field_attrs)
+}
+
+fn generate_debug_trait(st: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let container_attrs = get_container_debug_attrs(st)?;
     let struct_name_ident = &st.ident;
+    let struct_name_str = container_attrs.rename.clone().unwrap_or_else(|| struct_name_ident.to_string());
+
+    // 整体上指定了输出方式的，不再逐个字段格式化。
+    // 联合体无法知道当前哪个字段有效，只能用这种方式输出
+    let fmt_body_stream = if container_attrs.opaque {
+        quote!(fmt.debug_struct(#struct_name_str).finish_non_exhaustive())
+    } else if let Some(ref with) = container_attrs.with {
+        quote!(#with(self, fmt))
+    } else if container_attrs.transparent {
+        // 直接转发给唯一携带数据的字段，字段上的`redact`、`with`、自定义格式等属性照常生效。
+        // 紧凑结构体的字段不能引用，先用块表达式复制一份
+        let (idx, field) = get_transparent_field(st)?;
+        let field_attrs = get_transparent_field_attrs(field)?;
+        let member = match field.ident {
+            Some(ref ident) => quote!(#ident),
+            None => {
+                let index = syn::Index::from(idx);
+                quote!(#index)
+            }
+        };
+        let packed = is_packed_struct(st);
+        let mut uses_custom_formatter = false;
+        let value = generate_field_debug_value(
+            field,
+            field_attrs,
+            || if packed { quote!({ self.#member }) } else { quote!(self.#member) },
+            &mut uses_custom_formatter,
+        );
+        let fmt_body_stream = quote!(std::fmt::Debug::fmt(#value, fmt));
+        if uses_custom_formatter {
+            with_custom_formatter_wrapper(fmt_body_stream)
+        } else {
+            fmt_body_stream
+        }
+    } else if let syn::Data::Union(_) = st.data {
+        return Err(syn::Error::new_spanned(
+            struct_name_ident,
+            r#"CustomDebug cannot tell which field of a union is active, use `#[debug(opaque)]` or `#[debug(with = "...")]`"#,
+        ));
+    } else {
+        generate_debug_trait_core(st, &container_attrs)?
+    };
 
     // 从输入的派生宏语法树节点获取被修饰的输入结构体的泛型信息
     let mut generics_param_to_modify = st.generics.clone();
 
     // 判定是否设置了限定条件干预，如果设定了，则不进行推断，直接使用用户给出的限定条件放到where子句中
-    if let Some(hatch) = get_struct_escape_hatch(st) {
+    if let Some(hatch) = container_attrs.bound {
         generics_param_to_modify.make_where_clause();
         generics_param_to_modify
             .where_clause
             .as_mut()
             .unwrap()
             .predicates
-            .push(syn::parse_str(hatch.as_str()).unwrap());
-    } else {
-        let fields = get_fields_from_derive_input(st)?;
-        let mut field_type_names = Vec::new();
-        let mut phantomdata_type_param_names = Vec::new();
-        for field in fields {
-            if let Some(s) = get_field_type_name(field)? {
-                field_type_names.push(s);
-            }
-            if let Some(s) = get_phantomdata_generic_type_name(field)? {
-                phantomdata_type_param_names.push(s);
+            .extend(hatch);
+    } else if container_attrs.transparent {
+        // 只有被转发的字段需要实现`Debug`，字段上给出了限定条件的使用给出的限定条件
+        let (_, field) = get_transparent_field(st)?;
+        let field_attrs = get_transparent_field_attrs(field)?;
+        let reads_field = field_attrs.redact.is_none() && field_attrs.expr.is_none();
+        match field_attrs.bound {
+            Some(bound) => generics_param_to_modify.make_where_clause().predicates.extend(bound),
+            None if field_attrs.needs_debug_bound() => {
+                for bound in field_attrs.format_traits() {
+                    if field_attrs.limit.is_some() {
+                        add_param_bounds(st, &mut generics_param_to_modify, &[field], &bound);
+                    } else {
+                        add_inferred_bounds(st, &mut generics_param_to_modify, &[field], &bound);
+                    }
+                }
             }
+            None => {}
         }
-
-        // 我们需要对每一个泛型参数都添加一个`Debug` Trait 限定
-        let associated_types_map = get_generic_associated_types(st);
-        for g in generics_param_to_modify.params.iter_mut() {
-            if let syn::GenericParam::Type(t) = g {
-                let type_param_name = t.ident.to_string();
-                if phantomdata_type_param_names.contains(&type_param_name) && !field_type_names.contains(&type_param_name) {
+        if is_packed_struct(st) && reads_field {
+            add_inferred_bounds(st, &mut generics_param_to_modify, &[field], &parse_quote!(std::marker::Copy));
+        }
+    } else if !container_attrs.opaque && container_attrs.with.is_none() {
+        // 被跳过、脱敏或使用自定义格式化函数的字段不会调用字段类型的`Debug`实现，因此不参与限定条件的推断
+        // 字段或者枚举分支上给出了限定条件的，用给出的限定条件替换掉从这个字段（分支）推断出的限定条件
+        let mut fields = Vec::new();
+        let mut element_fields = Vec::new();
+        let mut field_bounds = Vec::new();
+        let mut copied_fields = Vec::new();
+        for (variant_attrs, group) in get_field_groups(st)? {
+            if variant_attrs.skip {
+                continue;
+            }
+            if let Some(bound) = variant_attrs.bound {
+                field_bounds.extend(bound);
+                continue;
+            }
+            for field in group {
+                let field_attrs = get_field_debug_attrs(field)?;
+                // `skip_markers`省略的标记字段不会被格式化，也就不需要任何约束
+                if container_attrs.skip_markers && !field_attrs.show && is_marker_type(&field.ty) {
                     continue;
                 }
-                // 如果是关联类型，就不要对泛型参数`T`本身再添加约束了,除非`T`本身也被直接使用了
-                if associated_types_map.contains_key(&type_param_name) && !field_type_names.contains(&type_param_name){
-                    continue
+                if !field_attrs.skip && field_attrs.redact.is_none() && field_attrs.expr.is_none() {
+                    copied_fields.push(field);
+                }
+                if let Some(bound) = field_attrs.bound {
+                    field_bounds.extend(bound);
+                } else if field_attrs.needs_debug_bound() && field_attrs.limit.is_some() {
+                    group_by_format_trait(&mut element_fields, field, &field_attrs);
+                } else if field_attrs.needs_debug_bound() {
+                    group_by_format_trait(&mut fields, field, &field_attrs);
                 }
-                t.bounds.push(parse_quote!(std::fmt::Debug));
             }
         }
 
-        // 关联类型的约束要放到where子句里
-        generics_param_to_modify.make_where_clause();
-        for (_, associated_types) in associated_types_map {
-            for associated_type in associated_types {
-                generics_param_to_modify.where_clause.as_mut().unwrap().predicates.push(parse_quote!(#associated_type:std::fmt::Debug));
-            }
+        generics_param_to_modify.make_where_clause().predicates.extend(field_bounds);
+        for (bound, fields) in fields {
+            add_inferred_bounds(st, &mut generics_param_to_modify, &fields, &bound);
+        }
+        for (bound, fields) in element_fields {
+            add_param_bounds(st, &mut generics_param_to_modify, &fields, &bound);
+        }
+        // 紧凑结构体的字段要先复制出来才能格式化
+        if is_packed_struct(st) {
+            add_inferred_bounds(st, &mut generics_param_to_modify, &copied_fields, &parse_quote!(std::marker::Copy));
         }
     }
 
@@ -124,10 +633,25 @@ fn generate_debug_trait(st: &syn::DeriveInput) -> syn::Result<proc_macro2::Token
         }
     };
 
-    return Ok(ret_stream);
+    Ok(ret_stream)
 }
 
-fn get_custom_format_of_field(field: &syn::Field) -> syn::Result<Option<String>> {
+// 字段按照自定义格式要求的Trait分组，每组分别推断限定条件，例如`#[debug = "{:x}"]`要求`LowerHex`
+fn group_by_format_trait<'a>(
+    groups: &mut Vec<(syn::Path, Vec<&'a syn::Field>)>,
+    field: &'a syn::Field,
+    field_attrs: &FieldDebugAttrs,
+) {
+    for bound in field_attrs.format_traits() {
+        match groups.iter_mut().find(|(b, _)| *b == bound) {
+            Some((_, fields)) => fields.push(field),
+            None => groups.push((bound, vec![field])),
+        }
+    }
+}
+
+fn get_custom_format_of_field(field: &syn::Field) -> syn::Result<Option<syn::LitStr>> {
+    let mut format = None;
     for attr in &field.attrs {
         if let Ok(syn::Meta::NameValue(syn::MetaNameValue {
             ref path,
@@ -137,63 +661,505 @@ fn get_custom_format_of_field(field: &syn::Field) -> syn::Result<Option<String>>
         {
             if path.is_ident("debug") {
                 if let syn::Lit::Str(ref ident_str) = lit {
-                    return Ok(Some(ident_str.value()));
+                    if format.is_some() {
+                        return Err(syn::Error::new_spanned(attr, r#"duplicate `#[debug = "..."]` attribute"#));
+                    }
+                    validate_custom_format(ident_str)?;
+                    format = Some(ident_str.clone());
                 }
             }
         }
     }
-    Ok(None)
+    Ok(format)
 }
 
-fn get_phantomdata_generic_type_name(field: &syn::Field) -> syn::Result<Option<String>> {
-    if let syn::Type::Path(syn::TypePath{path: syn::Path{ref segments, ..}, ..}) = field.ty {
-        if let Some(syn::PathSegment{ref ident, ref arguments}) = segments.last() {
-            if ident == "PhantomData" {
-                if let syn::PathArguments::AngleBracketed(syn::AngleBracketedGenericArguments {args, ..}) = arguments {
-                    if let Some(syn::GenericArgument::Type(syn::Type::Path(ref gp))) = args.first() {
-                        if let Some(generic_ident) = gp.path.segments.first() {
-                            return Ok(Some(generic_ident.ident.to_string()))
-                        }
-                    }
+// 在宏展开时检查自定义格式串，否则错误要等到编译器检查生成的`format_args!`时才报出来，位置也不直观。
+// 字段的值是唯一的参数，所以格式串中只能有一个不带参数名的占位符
+fn validate_custom_format(lit: &syn::LitStr) -> syn::Result<()> {
+    let format = lit.value();
+    let placeholders = parse_placeholders(&format).map_err(|e| syn::Error::new_spanned(lit, e))?;
+    let placeholder = match placeholders.as_slice() {
+        [placeholder] => placeholder,
+        [] => {
+            return Err(syn::Error::new_spanned(lit, "custom format string must contain a `{}` placeholder for the field value"));
+        }
+        _ => {
+            return Err(syn::Error::new_spanned(
+                lit,
+                format!("custom format string must contain exactly one placeholder, found {}", placeholders.len()),
+            ));
+        }
+    };
+    let arg = &format[placeholder.arg.clone()];
+    if !arg.is_empty() && arg != "0" {
+        return Err(syn::Error::new_spanned(
+            lit,
+            format!("placeholder `{{{}}}` refers to an unknown argument, the field value is the only argument", arg),
+        ));
+    }
+    if let Some(ref spec) = placeholder.spec {
+        validate_format_spec(&format[spec.clone()]).map_err(|e| syn::Error::new_spanned(lit, e))?;
+    }
+    Ok(())
+}
+
+// 格式说明的顺序是`[[fill]align][sign]['#']['0'][width]['.' precision]type`，
+// 宽度和精度只支持直接写出的数字，`1$`和`.*`都需要额外的参数
+fn validate_format_spec(spec: &str) -> Result<(), String> {
+    let is_align = |c: char| c == '<' || c == '^' || c == '>';
+    let count_digits = |s: &str| s.len() - s.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let mut rest = spec;
+    let mut chars = rest.chars();
+    match (chars.next(), chars.next()) {
+        (Some(fill), Some(align)) if is_align(align) => rest = &rest[fill.len_utf8() + 1..],
+        (Some(align), _) if is_align(align) => rest = &rest[1..],
+        _ => {}
+    }
+    rest = rest.strip_prefix(['+', '-']).unwrap_or(rest);
+    rest = rest.strip_prefix('#').unwrap_or(rest);
+    rest = rest.strip_prefix('0').unwrap_or(rest);
+    rest = &rest[count_digits(rest)..];
+    if rest.starts_with('$') {
+        return Err("width arguments like `1$` are not supported, the field value is the only argument".to_string());
+    }
+    if let Some(precision) = rest.strip_prefix('.') {
+        if precision.starts_with('*') {
+            return Err("precision `.*` is not supported, the field value is the only argument".to_string());
+        }
+        let digits = count_digits(precision);
+        if digits == 0 {
+            return Err(format!("invalid format spec `{}`, expected a precision after `.`", spec));
+        }
+        rest = &precision[digits..];
+        if rest.starts_with('$') {
+            return Err("precision arguments like `.1$` are not supported, the field value is the only argument".to_string());
+        }
+    }
+    match rest {
+        "" | "?" | "x?" | "X?" | "x" | "X" | "o" | "b" | "e" | "E" | "p" => Ok(()),
+        _ => Err(format!(
+            "invalid format spec `{}`, unknown format trait `{}`, expected one of `?`, `x?`, `X?`, `x`, `X`, `o`, `b`, `e`, `E` or `p`",
+            spec, rest,
+        )),
+    }
+}
+
+// 为`{:?}`这类调试格式的占位符补上`#`标记，得到`{:#?}`时使用的格式串。
+// 其他类型的占位符（例如`{:08b}`）加上`#`会改变输出内容（添加`0b`前缀），所以保持原样
+fn make_alternate_format(format: &str) -> String {
+    let spec_range = match parse_placeholders(format) {
+        Ok(placeholders) => placeholders.into_iter().find_map(|p| p.spec),
+        Err(_) => None,
+    };
+    if let Some(spec_range) = spec_range {
+        let spec = &format[spec_range.clone()];
+        if spec.ends_with('?') {
+            // 格式说明的顺序是`[[fill]align][sign]['#']['0'][width]['.' precision]type`
+            let is_align = |c: char| c == '<' || c == '^' || c == '>';
+            let mut pos = 0;
+            let mut chars = spec.chars();
+            if let Some(first) = chars.next() {
+                if chars.next().is_some_and(is_align) {
+                    pos = first.len_utf8() + 1;
+                } else if is_align(first) {
+                    pos = 1;
                 }
             }
+            if spec[pos..].starts_with(['+', '-']) {
+                pos += 1;
+            }
+            if !spec[pos..].starts_with('#') {
+                let insert_at = spec_range.start + pos;
+                return format!("{}#{}", &format[..insert_at], &format[insert_at..]);
+            }
+        }
+    }
+    format.to_string()
+}
+
+// 格式串中的一个占位符`{arg:spec}`，记录了参数部分和格式说明部分在格式串中的字节位置
+struct Placeholder {
+    arg: std::ops::Range<usize>,
+    spec: Option<std::ops::Range<usize>>,
+}
+
+// 找出格式串中所有的占位符，`{{`和`}}`是转义的大括号
+fn parse_placeholders(format: &str) -> Result<Vec<Placeholder>, String> {
+    let mut placeholders = Vec::new();
+    let mut idx = 0;
+    while idx < format.len() {
+        let rest = &format[idx..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            idx += 2;
+        } else if rest.starts_with('}') {
+            return Err("unmatched `}` in format string, use `}}` to print a literal brace".to_string());
+        } else if rest.starts_with('{') {
+            let close = match rest.find('}') {
+                Some(offset) => idx + offset,
+                None => return Err("unmatched `{` in format string, use `{{` to print a literal brace".to_string()),
+            };
+            let inner_start = idx + 1;
+            let placeholder = match format[inner_start..close].find(':') {
+                Some(colon) => Placeholder {
+                    arg: inner_start..inner_start + colon,
+                    spec: Some(inner_start + colon + 1..close),
+                },
+                None => Placeholder {
+                    arg: inner_start..close,
+                    spec: None,
+                },
+            };
+            placeholders.push(placeholder);
+            idx = close + 1;
+        } else {
+            idx += rest.chars().next().unwrap().len_utf8();
+        }
+    }
+    Ok(placeholders)
+}
+
+// 字段上`#[debug(...)]`形式的属性的解析结果
+#[derive(Default)]
+struct FieldDebugAttrs {
+    skip: bool,
+    redact: Option<String>,
+    with: Option<syn::ExprPath>,
+    bound: Option<Vec<syn::WherePredicate>>,
+    format: Option<syn::LitStr>,
+    alt: Option<syn::LitStr>,
+    rename: Option<String>,
+    limit: Option<usize>,
+    len_only: bool,
+    show: bool,
+    expr: Option<proc_macro2::TokenStream>,
+}
+
+impl FieldDebugAttrs {
+    fn needs_debug_bound(&self) -> bool {
+        !self.skip && self.redact.is_none() && self.with.is_none() && !self.len_only && self.expr.is_none()
+    }
+
+    // 格式化字段的值需要实现的Trait，由`format`和`alt`中占位符的格式说明决定，没有自定义格式时是`Debug`。
+    // 没有给出`alt`时，`{:#?}`使用的格式串只是多了`#`标记，要求的Trait和`format`相同
+    fn format_traits(&self) -> Vec<syn::Path> {
+        let format_trait = |format: &Option<syn::LitStr>| -> syn::Path {
+            let format = match format {
+                Some(format) => format.value(),
+                None => return parse_quote!(std::fmt::Debug),
+            };
+            let placeholder = parse_placeholders(&format).ok().and_then(|p| p.into_iter().next());
+            let spec = placeholder.and_then(|p| p.spec).map(|spec| &format[spec]).unwrap_or("");
+            display::get_format_trait(spec)
+        };
+        let mut traits = vec![format_trait(&self.format)];
+        if self.alt.is_some() {
+            let alt_trait = format_trait(&self.alt);
+            if !traits.contains(&alt_trait) {
+                traits.push(alt_trait);
+            }
+        }
+        traits
+    }
+}
+
+const DEFAULT_REDACT_PLACEHOLDER: &str = "[REDACTED]";
+
+fn get_field_debug_attrs(field: &syn::Field) -> syn::Result<FieldDebugAttrs> {
+    let mut ret = FieldDebugAttrs::default();
+    let mut seen_keys = Vec::new();
+    for item in get_attr_items(&field.attrs, "debug")? {
+        reject_duplicate_key(&item, &mut seen_keys)?;
+        match item {
+            syn::NestedMeta::Meta(syn::Meta::Path(ref path)) if path.is_ident("skip") => {
+                ret.skip = true;
+            }
+            syn::NestedMeta::Meta(syn::Meta::Path(ref path)) if path.is_ident("redact") => {
+                ret.redact = Some(DEFAULT_REDACT_PLACEHOLDER.to_string());
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                ref path,
+                lit: syn::Lit::Str(ref lit),
+                ..
+            })) if path.is_ident("redact") => {
+                ret.redact = Some(lit.value());
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                ref path,
+                lit: syn::Lit::Str(ref lit),
+                ..
+            })) if path.is_ident("with") => {
+                ret.with = Some(lit.parse()?);
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                ref path,
+                lit: syn::Lit::Str(ref lit),
+                ..
+            })) if path.is_ident("bound") => {
+                ret.bound = Some(parse_bound_predicates(lit)?);
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                ref path,
+                lit: syn::Lit::Str(ref lit),
+                ..
+            })) if path.is_ident("format") => {
+                validate_custom_format(lit)?;
+                ret.format = Some(lit.clone());
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                ref path,
+                lit: syn::Lit::Str(ref lit),
+                ..
+            })) if path.is_ident("alt") => {
+                validate_custom_format(lit)?;
+                ret.alt = Some(lit.clone());
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                ref path,
+                lit: syn::Lit::Str(ref lit),
+                ..
+            })) if path.is_ident("rename") => {
+                ret.rename = Some(lit.value());
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                ref path,
+                lit: syn::Lit::Int(ref lit),
+                ..
+            })) if path.is_ident("limit") => {
+                ret.limit = Some(lit.base10_parse()?);
+            }
+            syn::NestedMeta::Meta(syn::Meta::Path(ref path)) if path.is_ident("len_only") => {
+                ret.len_only = true;
+            }
+            syn::NestedMeta::Meta(syn::Meta::Path(ref path)) if path.is_ident("show") => {
+                ret.show = true;
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                ref path,
+                lit: syn::Lit::Str(ref lit),
+                ..
+            })) if path.is_ident("expr") => {
+                ret.expr = Some(lit.parse()?);
+            }
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "expected one of `skip`, `redact`, `with`, `bound`, `format`, `alt`, `rename`, `limit`, `len_only`, `show` or `expr`",
+                ));
+            }
+        }
+    }
+    if let Some(format) = get_custom_format_of_field(field)? {
+        if ret.format.is_some() {
+            return Err(syn::Error::new_spanned(format, "custom format given both as `#[debug = \"...\"]` and `#[debug(format = \"...\")]`"));
         }
+        ret.format = Some(format);
+    }
+    let strategies = [ret.skip, ret.redact.is_some(), ret.with.is_some(), ret.limit.is_some(), ret.len_only, ret.expr.is_some()];
+    if strategies.iter().filter(|s| **s).count() > 1 {
+        return Err(syn::Error::new_spanned(
+            field,
+            "`skip`, `redact`, `with`, `limit`, `len_only` and `expr` cannot be combined on the same field",
+        ));
     }
-    return Ok(None);
+    if ret.skip && ret.show {
+        return Err(syn::Error::new_spanned(field, "`skip` and `show` cannot be combined on the same field"));
+    }
+    // `expr`给出的值同样可以使用自定义格式
+    let formats_field_value = ret.needs_debug_bound() || ret.expr.is_some();
+    if !formats_field_value && (ret.format.is_some() || ret.alt.is_some()) {
+        return Err(syn::Error::new_spanned(
+            field,
+            "a custom format string cannot be combined with `skip`, `redact`, `with` or `len_only`",
+        ));
+    }
+    Ok(ret)
 }
 
-fn get_field_type_name(field: &syn::Field) -> syn::Result<Option<String>> {
-    if let syn::Type::Path(syn::TypePath{path: syn::Path{ ref segments, ..}, ..}) = field.ty {
-        if let Some(syn::PathSegment {ref ident, ..}) = segments.last() {
-            return Ok(Some(ident.to_string()));
+// 枚举分支上`#[debug(...)]`形式的属性的解析结果
+#[derive(Default)]
+struct VariantDebugAttrs {
+    skip: bool,
+    bound: Option<Vec<syn::WherePredicate>>,
+    rename: Option<String>,
+}
+
+fn get_variant_debug_attrs(variant: &syn::Variant) -> syn::Result<VariantDebugAttrs> {
+    let mut ret = VariantDebugAttrs::default();
+    for attr in &variant.attrs {
+        if let Ok(syn::Meta::NameValue(ref nv)) = attr.parse_meta() {
+            if nv.path.is_ident("debug") {
+                return Err(syn::Error::new_spanned(attr, r#"expected `#[debug(...)]` on a variant, `#[debug = "..."]` is only allowed on fields"#));
+            }
+        }
+    }
+    let mut seen_keys = Vec::new();
+    for item in get_attr_items(&variant.attrs, "debug")? {
+        reject_duplicate_key(&item, &mut seen_keys)?;
+        match item {
+            syn::NestedMeta::Meta(syn::Meta::Path(ref path)) if path.is_ident("skip") => {
+                ret.skip = true;
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                ref path,
+                lit: syn::Lit::Str(ref lit),
+                ..
+            })) if path.is_ident("bound") => {
+                ret.bound = Some(parse_bound_predicates(lit)?);
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                ref path,
+                lit: syn::Lit::Str(ref lit),
+                ..
+            })) if path.is_ident("rename") => {
+                ret.rename = Some(lit.value());
+            }
+            other => {
+                return Err(syn::Error::new_spanned(other, "expected one of `skip`, `bound` or `rename`"));
+            }
         }
     }
-    return Ok(None);
+    if ret.skip && ret.bound.is_some() {
+        return Err(syn::Error::new_spanned(variant, "`skip` and `bound` cannot be combined on the same variant"));
+    }
+    Ok(ret)
 }
 
 // 定义一个用于实现`Visit` Trait的结构体，结构体中定义了一些字段，用于存储筛选条件以及筛选结果
 struct TypePathVisitor {
     generic_type_names: Vec<String>, // 这个是筛选条件，里面记录了所有的泛型参数的名字，例如`T`,`U`等
-    associated_types: HashMap<String, Vec<syn::TypePath>>, // 这里记录了所有满足条件的语法树节点
+    bounded_type_names: HashSet<String>, // 这里记录了需要添加`Debug`约束的泛型参数的名字
+    associated_types: Vec<syn::TypePath>, // 这里记录了需要添加`Debug`约束的关联类型，例如`T::Value`
+    hits: usize, // 找到的泛型参数和关联类型出现的次数，用来判断某个类型的参数中是否用到了它们
+    has_local_generic_type: bool, // 是否有标准库以外的泛型类型用到了泛型参数，例如`Two<T>`
 }
 
 impl <'ast> Visit<'ast> for TypePathVisitor {
     fn visit_type_path(&mut self, i: &'ast syn::TypePath) {
-        
-        if i.path.segments.len() >= 2 {
-            let generic_type_name = i.path.segments[0].ident.to_string();
-            if self.generic_type_names.contains(&generic_type_name) {
-                // 如果满足上面的两个筛选条件，那么就把结果存起来
-                self.associated_types.entry(generic_type_name).or_insert(Vec::new()).push(i.clone());
+        // 形如`<T as Trait>::Value`的关联类型
+        if let Some(ref qself) = i.qself {
+            if let syn::Type::Path(ref qself_ty) = *qself.ty {
+                if qself_ty.qself.is_none() && self.generic_type_names.contains(&qself_ty.path.segments[0].ident.to_string()) {
+                    self.hits += 1;
+                    self.push_associated_type(i);
+                    return;
+                }
+            }
+        } else {
+            let first_segment_name = i.path.segments[0].ident.to_string();
+            if self.generic_type_names.contains(&first_segment_name) {
+                self.hits += 1;
+                if i.path.segments.len() >= 2 {
+                    // 形如`T::Value`的关联类型，只约束关联类型本身，不约束`T`
+                    self.push_associated_type(i);
+                } else {
+                    self.bounded_type_names.insert(first_segment_name);
+                }
+                return;
             }
         }
+
+        // `PhantomData<T>`无论`T`是什么都实现了`Debug`，不需要再往里面看了
+        if i.path.segments.last().unwrap().ident == "PhantomData" {
+            return;
+        }
         // Visit 模式要求在当前节点访问完成后，继续调用默认实现的visit方法，从而遍历到所有的
         // 必须调用这个函数，否则遍历到这个节点就不再往更深层走了
+        let hits = self.hits;
         visit::visit_type_path(self, i);
+        if self.hits > hits && !is_std_type_path(&i.path) {
+            self.has_local_generic_type = true;
+        }
+    }
+
+    // 裸指针和函数指针的`Debug`实现只输出地址，与指向的类型无关
+    fn visit_type_ptr(&mut self, _: &'ast syn::TypePtr) {}
+
+    fn visit_type_bare_fn(&mut self, _: &'ast syn::TypeBareFn) {}
+}
+
+impl TypePathVisitor {
+    fn push_associated_type(&mut self, i: &syn::TypePath) {
+        if !self.associated_types.contains(i) {
+            self.associated_types.push(i.clone());
+        }
+    }
+}
+
+// 标准库中常用的泛型类型，不带路径前缀时按名字识别
+const STD_GENERIC_TYPES: &[&str] = &[
+    "Box", "Vec", "Option", "Result", "Rc", "Arc", "Weak", "Cell", "RefCell", "OnceCell", "Mutex", "RwLock",
+    "HashMap", "HashSet", "BTreeMap", "BTreeSet", "VecDeque", "BinaryHeap", "LinkedList", "Cow", "Reverse",
+    "Wrapping", "Pin", "NonNull", "ManuallyDrop", "Peekable", "Range", "RangeInclusive",
+];
+
+// 判断路径是否指向标准库中的类型：`std::`、`core::`、`alloc::`开头的路径，或者上面列出的类型名
+fn is_std_type_path(path: &syn::Path) -> bool {
+    let first = &path.segments[0].ident;
+    if first == "std" || first == "core" || first == "alloc" {
+        return true;
+    }
+    path.segments.len() == 1 && STD_GENERIC_TYPES.iter().any(|name| first == name)
+}
+
+// 完整地遍历每个字段的类型，只对真正会被格式化输出的字段添加`bound`约束。
+// 字段类型只由标准库类型和泛型参数组成时，约束直接加在字段类型上，例如`Cell<T>: Debug`，
+// 这样`Cell<T>`要求的`T: Copy`、`Peekable<I>`要求的`I::Item: Debug`都能满足。
+// 字段类型中用到了本crate中定义的泛型类型（例如`Two<T>`）时，这个类型可能和正在派生的类型互相引用，
+// 约束加在字段类型上会让编译器陷入循环，这时退回到约束其中出现的泛型参数和关联类型
+fn add_inferred_bounds(st: &syn::DeriveInput, generics: &mut syn::Generics, fields: &[&syn::Field], bound: &syn::Path) {
+    let mut param_bounded_fields = Vec::new();
+    let mut field_types: Vec<&syn::Type> = Vec::new();
+    for field in fields {
+        let visitor = visit_field_type(st, &field.ty);
+        // 没有用到泛型参数的字段类型不需要任何约束
+        if visitor.hits == 0 {
+            continue;
+        }
+        if visitor.has_local_generic_type {
+            param_bounded_fields.push(*field);
+        } else if !field_types.contains(&&field.ty) {
+            field_types.push(&field.ty);
+        }
+    }
+
+    add_param_bounds(st, generics, &param_bounded_fields, bound);
+    let where_clause = generics.make_where_clause();
+    for field_type in field_types {
+        where_clause.predicates.push(parse_quote!(#field_type: #bound));
+    }
+}
+
+// 对字段类型中出现的泛型参数和关联类型添加`bound`约束。
+// 只格式化集合中元素的字段（`limit`）也用这种方式，因为集合类型本身不会被格式化
+fn add_param_bounds(st: &syn::DeriveInput, generics: &mut syn::Generics, fields: &[&syn::Field], bound: &syn::Path) {
+    let mut bounded_type_names = HashSet::new();
+    let mut associated_types: Vec<syn::TypePath> = Vec::new();
+    for field in fields {
+        let visitor = visit_field_type(st, &field.ty);
+        bounded_type_names.extend(visitor.bounded_type_names);
+        for associated_type in visitor.associated_types {
+            if !associated_types.contains(&associated_type) {
+                associated_types.push(associated_type);
+            }
+        }
+    }
+
+    for g in generics.params.iter_mut() {
+        if let syn::GenericParam::Type(t) = g {
+            if bounded_type_names.contains(&t.ident.to_string()) {
+                t.bounds.push(parse_quote!(#bound));
+            }
+        }
+    }
+
+    // 关联类型的约束要放到where子句里
+    let where_clause = generics.make_where_clause();
+    for associated_type in associated_types {
+        where_clause.predicates.push(parse_quote!(#associated_type: #bound));
     }
 }
 
-fn get_generic_associated_types(st: &syn::DeriveInput) -> HashMap<String, Vec<syn::TypePath>> {
+fn visit_field_type(st: &syn::DeriveInput, ty: &syn::Type) -> TypePathVisitor {
     // 首先构建筛选条件
     let origin_generic_param_names: Vec<String> = st.generics.params.iter().filter_map(|f| {
         if let syn::GenericParam::Type(ty) = f {
@@ -204,26 +1170,324 @@ fn get_generic_associated_types(st: &syn::DeriveInput) -> HashMap<String, Vec<sy
 
     let mut visitor = TypePathVisitor {
         generic_type_names: origin_generic_param_names,
-        associated_types: HashMap::new(),
+        bounded_type_names: HashSet::new(),
+        associated_types: Vec::new(),
+        hits: 0,
+        has_local_generic_type: false,
+    };
+    visitor.visit_type(ty);
+    visitor
+}
+
+// 结构体上`#[debug(...)]`形式的属性的解析结果
+#[derive(Default)]
+struct ContainerDebugAttrs {
+    bound: Option<Vec<syn::WherePredicate>>,
+    rename: Option<String>,
+    rename_all: Option<RenameRule>,
+    style: DebugStyle,
+    skip_markers: bool,
+    opaque: bool,
+    with: Option<syn::ExprPath>,
+    transparent: bool,
+    computed_fields: Vec<(String, proc_macro2::TokenStream)>,
+}
+
+// 调试输出的整体风格
+#[derive(Clone, Copy, Default)]
+enum DebugStyle {
+    // 与标准库派生的`Debug`一样，`Field { name: "F", .. }`
+    #[default]
+    Struct,
+    // 和`Struct`一样，但是忽略`{:#?}`，始终输出一行
+    Compact,
+    // logfmt格式的键值对，`name=F bitmask=0b00011100`
+    Logfmt,
+}
+
+impl DebugStyle {
+    fn from_lit(lit: &syn::LitStr) -> syn::Result<Self> {
+        match lit.value().as_str() {
+            "struct" => Ok(DebugStyle::Struct),
+            "compact" => Ok(DebugStyle::Compact),
+            "logfmt" => Ok(DebugStyle::Logfmt),
+            _ => Err(syn::Error::new_spanned(lit, r#"unknown style, expected one of "struct", "compact" or "logfmt""#)),
+        }
+    }
+}
+
+fn get_container_debug_attrs(st: &syn::DeriveInput) -> syn::Result<ContainerDebugAttrs> {
+    let mut ret = ContainerDebugAttrs::default();
+    for attr in &st.attrs {
+        if let Ok(syn::Meta::NameValue(ref nv)) = attr.parse_meta() {
+            if nv.path.is_ident("debug") {
+                return Err(syn::Error::new_spanned(attr, r#"expected `#[debug(...)]` on a struct, `#[debug = "..."]` is only allowed on fields"#));
+            }
+        }
+    }
+    let mut seen_keys = Vec::new();
+    for item in get_attr_items(&st.attrs, "debug")? {
+        // `field(...)`每次给出一个不同的计算值，可以出现多次
+        if !matches!(item, syn::NestedMeta::Meta(syn::Meta::List(ref list)) if list.path.is_ident("field")) {
+            reject_duplicate_key(&item, &mut seen_keys)?;
+        }
+        match item {
+            syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                ref path,
+                lit: syn::Lit::Str(ref lit),
+                ..
+            })) if path.is_ident("bound") => {
+                ret.bound = Some(parse_bound_predicates(lit)?);
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                ref path,
+                lit: syn::Lit::Str(ref lit),
+                ..
+            })) if path.is_ident("rename") => {
+                ret.rename = Some(lit.value());
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                ref path,
+                lit: syn::Lit::Str(ref lit),
+                ..
+            })) if path.is_ident("rename_all") => {
+                ret.rename_all = Some(RenameRule::from_lit(lit)?);
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                ref path,
+                lit: syn::Lit::Str(ref lit),
+                ..
+            })) if path.is_ident("style") => {
+                ret.style = DebugStyle::from_lit(lit)?;
+            }
+            syn::NestedMeta::Meta(syn::Meta::Path(ref path)) if path.is_ident("skip_markers") => {
+                ret.skip_markers = true;
+            }
+            syn::NestedMeta::Meta(syn::Meta::Path(ref path)) if path.is_ident("opaque") => {
+                ret.opaque = true;
+            }
+            syn::NestedMeta::Meta(syn::Meta::Path(ref path)) if path.is_ident("transparent") => {
+                ret.transparent = true;
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                ref path,
+                lit: syn::Lit::Str(ref lit),
+                ..
+            })) if path.is_ident("with") => {
+                ret.with = Some(lit.parse()?);
+            }
+            syn::NestedMeta::Meta(syn::Meta::List(ref list)) if list.path.is_ident("field") => {
+                ret.computed_fields.push(get_computed_field(list)?);
+            }
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "expected one of `bound`, `rename`, `rename_all`, `style`, `skip_markers`, `opaque`, `with`, `transparent` or `field`",
+                ));
+            }
+        }
+    }
+    if [ret.opaque, ret.with.is_some(), ret.transparent].iter().filter(|s| **s).count() > 1 {
+        return Err(syn::Error::new_spanned(&st.ident, "`opaque`, `with` and `transparent` cannot be combined"));
+    }
+    // 这三种方式都不逐个输出字段，字段名、输出风格和计算值等配置不会生效。
+    // 只有`opaque`输出的`Name { .. }`中还会用到`rename`
+    let strategy = if ret.opaque {
+        Some("opaque")
+    } else if ret.with.is_some() {
+        Some("with")
+    } else if ret.transparent {
+        Some("transparent")
+    } else {
+        None
     };
+    if let Some(strategy) = strategy {
+        for item in get_attr_items(&st.attrs, "debug")? {
+            if let syn::NestedMeta::Meta(ref meta) = item {
+                let path = meta.path();
+                let ignored = ["rename_all", "style", "skip_markers", "field"].iter().any(|key| path.is_ident(key))
+                    || (path.is_ident("rename") && !ret.opaque);
+                if ignored {
+                    return Err(syn::Error::new_spanned(
+                        path,
+                        format!("`{}` has no effect together with `{}`", quote!(#path), strategy),
+                    ));
+                }
+            }
+        }
+    }
+    Ok(ret)
+}
 
-    // 以st语法树节点为起点，开始Visit整个st节点的子节点
-    visitor.visit_derive_input(st);
-    return visitor.associated_types;
+// 解析`field(name = "len", expr = "self.buf.len()")`，得到额外输出的名字和计算它的值的表达式。
+// 表达式只做词法解析，原样放进生成的代码里，语法和类型错误交给编译器在字符串的位置上报告
+fn get_computed_field(list: &syn::MetaList) -> syn::Result<(String, proc_macro2::TokenStream)> {
+    let mut name = None;
+    let mut expr = None;
+    let mut seen_keys = Vec::new();
+    for item in &list.nested {
+        reject_duplicate_key(item, &mut seen_keys)?;
+        match item {
+            syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                ref path,
+                lit: syn::Lit::Str(ref lit),
+                ..
+            })) if path.is_ident("name") => {
+                name = Some(lit.value());
+            }
+            syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                ref path,
+                lit: syn::Lit::Str(ref lit),
+                ..
+            })) if path.is_ident("expr") => {
+                expr = Some(lit.parse()?);
+            }
+            other => {
+                return Err(syn::Error::new_spanned(other, r#"expected `name = "..."` or `expr = "..."`"#));
+            }
+        }
+    }
+    match (name, expr) {
+        (Some(name), Some(expr)) => Ok((name, expr)),
+        _ => Err(syn::Error::new_spanned(list, r#"expected `field(name = "...", expr = "...")`"#)),
+    }
+}
+
+// `rename_all`支持的命名风格，与serde保持一致：结构体上的规则作用在字段名上，枚举上的规则作用在分支名上
+#[derive(Clone, Copy)]
+enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
 }
 
-fn get_struct_escape_hatch(st: &syn::DeriveInput) -> Option<String> {
-    if let Some(inner_attr) = st.attrs.last() {
-        if let Ok(syn::Meta::List(syn::MetaList {nested, ..})) = inner_attr.parse_meta(){
-            if let Some(syn::NestedMeta::Meta(syn::Meta::NameValue(path_value))) = nested.last() {
-                if path_value.path.is_ident("bound") {
-                    if let syn::Lit::Str(ref lit) = path_value.lit {
-                        return Some(lit.value());
+const RENAME_RULES: &[(&str, RenameRule)] = &[
+    ("lowercase", RenameRule::Lower),
+    ("UPPERCASE", RenameRule::Upper),
+    ("PascalCase", RenameRule::Pascal),
+    ("camelCase", RenameRule::Camel),
+    ("snake_case", RenameRule::Snake),
+    ("SCREAMING_SNAKE_CASE", RenameRule::ScreamingSnake),
+    ("kebab-case", RenameRule::Kebab),
+    ("SCREAMING-KEBAB-CASE", RenameRule::ScreamingKebab),
+];
+
+impl RenameRule {
+    fn from_lit(lit: &syn::LitStr) -> syn::Result<Self> {
+        let value = lit.value();
+        for (name, rule) in RENAME_RULES {
+            if value == *name {
+                return Ok(*rule);
+            }
+        }
+        let names: Vec<_> = RENAME_RULES.iter().map(|(name, _)| format!("\"{}\"", name)).collect();
+        Err(syn::Error::new_spanned(lit, format!("unknown rename rule, expected one of {}", names.join(", "))))
+    }
+
+    // 字段名按照Rust的惯例是snake_case的
+    fn apply_to_field(self, field: &str) -> String {
+        match self {
+            RenameRule::Lower | RenameRule::Snake => field.to_string(),
+            RenameRule::Upper => field.to_ascii_uppercase(),
+            RenameRule::Pascal => {
+                let mut pascal = String::new();
+                let mut capitalize = true;
+                for ch in field.chars() {
+                    if ch == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        pascal.push(ch.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        pascal.push(ch);
+                    }
+                }
+                pascal
+            }
+            RenameRule::Camel => {
+                let pascal = RenameRule::Pascal.apply_to_field(field);
+                let mut chars = pascal.chars();
+                match chars.next() {
+                    Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+                    None => pascal,
+                }
+            }
+            RenameRule::ScreamingSnake => field.to_ascii_uppercase(),
+            RenameRule::Kebab => field.replace('_', "-"),
+            RenameRule::ScreamingKebab => field.to_ascii_uppercase().replace('_', "-"),
+        }
+    }
+
+    // 分支名按照Rust的惯例是PascalCase的
+    fn apply_to_variant(self, variant: &str) -> String {
+        match self {
+            RenameRule::Pascal => variant.to_string(),
+            RenameRule::Lower => variant.to_ascii_lowercase(),
+            RenameRule::Upper => variant.to_ascii_uppercase(),
+            RenameRule::Camel => {
+                let mut chars = variant.chars();
+                match chars.next() {
+                    Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+                    None => variant.to_string(),
+                }
+            }
+            RenameRule::Snake => {
+                let mut snake = String::new();
+                for (idx, ch) in variant.char_indices() {
+                    if idx > 0 && ch.is_uppercase() {
+                        snake.push('_');
                     }
+                    snake.push(ch.to_ascii_lowercase());
                 }
+                snake
             }
+            RenameRule::ScreamingSnake => RenameRule::Snake.apply_to_variant(variant).to_ascii_uppercase(),
+            RenameRule::Kebab => RenameRule::Snake.apply_to_variant(variant).replace('_', "-"),
+            RenameRule::ScreamingKebab => RenameRule::ScreamingSnake.apply_to_variant(variant).replace('_', "-"),
         }
     }
-    None
 }
 
+// 同一个配置项只能出现一次，分散在多个`#[debug(...)]`属性中也算重复，否则前面给出的值会被悄悄丢掉
+fn reject_duplicate_key(item: &syn::NestedMeta, seen_keys: &mut Vec<String>) -> syn::Result<()> {
+    let path = match item {
+        syn::NestedMeta::Meta(meta) => meta.path(),
+        syn::NestedMeta::Lit(_) => return Ok(()),
+    };
+    let key = quote!(#path).to_string();
+    if seen_keys.contains(&key) {
+        return Err(syn::Error::new_spanned(path, format!("duplicate `{}` attribute", key)));
+    }
+    seen_keys.push(key);
+    Ok(())
+}
+
+// 按路径筛选出所有`#[name(...)]`形式的属性，把其中的各个配置项依次取出来，
+// 其他派生宏的属性（例如`#[serde(...)]`）以及`#[repr(...)]`等都不会被误读
+fn get_attr_items(attrs: &[syn::Attribute], name: &str) -> syn::Result<Vec<syn::NestedMeta>> {
+    let mut items = Vec::new();
+    for attr in attrs {
+        if !attr.path.is_ident(name) {
+            continue;
+        }
+        // `#[debug = "..."]`形式的属性由`get_custom_format_of_field`处理
+        if let syn::Meta::List(syn::MetaList { nested, .. }) = attr.parse_meta()? {
+            items.extend(nested);
+        }
+    }
+    Ok(items)
+}
+
+// 把形如`"T::Value: Debug, U: Clone"`的字符串解析成多个where子句中的限定条件，
+// 解析失败时错误信息要指向属性中的字符串字面量，在字符串末尾出错时`parse_with`给出的位置是宏的调用处
+fn parse_bound_predicates(lit: &syn::LitStr) -> syn::Result<Vec<syn::WherePredicate>> {
+    let predicates = lit
+        .parse_with(syn::punctuated::Punctuated::<syn::WherePredicate, syn::Token!(,)>::parse_terminated)
+        .map_err(|e| syn::Error::new(lit.span(), format!("invalid bound: {}", e)))?;
+    Ok(predicates.into_iter().collect())
+}
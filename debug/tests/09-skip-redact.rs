@@ -0,0 +1,60 @@
+// Some fields must never end up in logs, for example passwords and access
+// tokens. Support two field attributes for keeping them out of the output.
+//
+// `#[debug(skip)]` omits the field entirely. The output then ends with `..`
+// to make it clear that not every field has been printed, which is what
+// `DebugStruct::finish_non_exhaustive` produces.
+//
+// `#[debug(redact)]` keeps the field name but prints a placeholder instead of
+// the value. A custom placeholder may be given as `#[debug(redact = "***")]`.
+//
+// Neither kind of field is formatted, so their types must not contribute a
+// `T: Debug` bound to the generated impl.
+
+use derive_debug::CustomDebug;
+use std::fmt::Debug;
+
+#[derive(CustomDebug)]
+pub struct Credentials<T, S> {
+    user: &'static str,
+    #[debug(redact)]
+    password: T,
+    #[debug(redact = "***")]
+    pin: u16,
+    #[debug(skip)]
+    token: S,
+}
+
+#[derive(CustomDebug)]
+pub struct Session {
+    id: u32,
+    #[debug(skip)]
+    secret: [u8; 4],
+}
+
+fn assert_debug<F: Debug>() {}
+
+fn main() {
+    // Does not implement Debug.
+    struct NotDebug;
+
+    assert_debug::<Credentials<NotDebug, NotDebug>>();
+
+    let c = Credentials {
+        user: "root",
+        password: NotDebug,
+        pin: 1234,
+        token: NotDebug,
+    };
+    let debug = format!("{:?}", c);
+    let expected = r#"Credentials { user: "root", password: [REDACTED], pin: ***, .. }"#;
+    assert_eq!(debug, expected);
+
+    let s = Session {
+        id: 7,
+        secret: [1, 2, 3, 4],
+    };
+    let debug = format!("{:?}", s);
+    let expected = r#"Session { id: 7, .. }"#;
+    assert_eq!(debug, expected);
+}
@@ -0,0 +1,76 @@
+// A format string is not always enough to render a field nicely. Accept a
+// field attribute #[debug(with = "path::to::function")] naming a function
+// with the signature
+//
+//     fn(&T, &mut fmt::Formatter) -> fmt::Result
+//
+// where T is the type of the field, and use it to format that field. One way
+// to plug such a function into `debug_struct` is a small wrapper type holding
+// a reference to the field and the function, whose Debug impl calls the
+// function.
+//
+// The Debug impl of the field's type is never used, so the field must not
+// contribute a `T: Debug` bound to the generated impl.
+
+use derive_debug::CustomDebug;
+use std::fmt::{self, Debug};
+use std::time::Duration;
+
+mod hex {
+    use std::fmt;
+
+    pub fn fmt(bytes: &Vec<u8>, f: &mut fmt::Formatter) -> fmt::Result {
+        for b in bytes {
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+fn humane(d: &Duration, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}s", d.as_secs())
+}
+
+pub trait Named {
+    fn name(&self) -> &'static str;
+}
+
+fn by_name<T: Named>(value: &T, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(value.name())
+}
+
+#[derive(CustomDebug)]
+pub struct Packet<T: Named> {
+    #[debug(with = "hex::fmt")]
+    payload: Vec<u8>,
+    #[debug(with = "humane")]
+    elapsed: Duration,
+    #[debug(with = "by_name")]
+    kind: T,
+}
+
+fn assert_debug<F: Debug>() {}
+
+fn main() {
+    // Does not implement Debug.
+    struct Ping;
+
+    impl Named for Ping {
+        fn name(&self) -> &'static str {
+            "ping"
+        }
+    }
+
+    assert_debug::<Packet<Ping>>();
+
+    let p = Packet {
+        payload: vec![0xde, 0xad, 0xbe, 0xef],
+        elapsed: Duration::from_secs(90),
+        kind: Ping,
+    };
+
+    let debug = format!("{:?}", p);
+    let expected = "Packet { payload: deadbeef, elapsed: 90s, kind: ping }";
+
+    assert_eq!(debug, expected);
+}
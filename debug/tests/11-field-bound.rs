@@ -0,0 +1,62 @@
+// The escape hatch from the previous test case may also be written on an
+// individual field. There it replaces only the bounds that would have been
+// inferred from that one field's type, and bounds inferred from the other
+// fields are kept:
+//
+//     impl<T: Trait, U> Debug for Wrapper<T, U>
+//     where
+//         T::Value: Debug,
+//         U: Debug,
+//     {...}
+//
+// Both forms of the attribute accept several comma-separated predicates, and
+// an empty string means that no bound is needed for that field at all.
+
+use derive_debug::CustomDebug;
+use std::fmt::Debug;
+
+pub trait Trait {
+    type Value;
+}
+
+#[derive(CustomDebug)]
+pub struct Wrapper<T: Trait, U> {
+    #[debug(bound = "T::Value: Debug")]
+    field: Field<T>,
+    normal: U,
+}
+
+#[derive(CustomDebug)]
+struct Field<T: Trait> {
+    values: Vec<T::Value>,
+}
+
+#[derive(CustomDebug)]
+#[debug(bound = "T::Value: Debug, U: Debug + Clone")]
+pub struct Pair<T: Trait, U> {
+    field: Field<T>,
+    normal: U,
+}
+
+#[derive(CustomDebug)]
+pub struct Erased<T> {
+    #[debug(bound = "")]
+    ptr: *const T,
+}
+
+fn assert_debug<F: Debug>() {}
+
+fn main() {
+    struct Id;
+
+    impl Trait for Id {
+        type Value = u8;
+    }
+
+    // Does not implement Debug.
+    struct NotDebug;
+
+    assert_debug::<Wrapper<Id, u8>>();
+    assert_debug::<Pair<Id, u8>>();
+    assert_debug::<Erased<NotDebug>>();
+}
@@ -0,0 +1,13 @@
+// A bound that does not parse as a list of where-clause predicates should be
+// reported at the attribute that contains it, instead of making the macro
+// panic.
+
+use derive_debug::CustomDebug;
+
+#[derive(CustomDebug)]
+pub struct Field<T> {
+    #[debug(bound = "T: Debug +, U")]
+    value: T,
+}
+
+fn main() {}
@@ -0,0 +1,49 @@
+// Every `#[debug(...)]` attribute on the struct is read, no matter where it is
+// placed among the other attributes, and several options may be combined in a
+// single attribute. Attributes that belong to other macros or to the compiler,
+// such as `#[repr(C)]` below, are left alone.
+//
+// Besides `bound`, the struct accepts `rename = "..."` to change the type name
+// printed in the output.
+
+use derive_debug::CustomDebug;
+use std::fmt::Debug;
+
+pub trait Trait {
+    type Value;
+}
+
+#[derive(CustomDebug)]
+#[debug(bound = "T::Value: Debug", rename = "Wrapped")]
+#[repr(C)]
+pub struct Wrapper<T: Trait> {
+    field: Field<T>,
+}
+
+#[derive(CustomDebug)]
+#[debug(rename = "F")]
+#[allow(dead_code)]
+#[debug(bound = "T::Value: Debug")]
+struct Field<T: Trait> {
+    values: Vec<T::Value>,
+}
+
+fn assert_debug<F: Debug>() {}
+
+fn main() {
+    struct Id;
+
+    impl Trait for Id {
+        type Value = u8;
+    }
+
+    assert_debug::<Wrapper<Id>>();
+
+    let w = Wrapper::<Id> {
+        field: Field { values: vec![1, 2] },
+    };
+    let debug = format!("{:?}", w);
+    let expected = "Wrapped { field: F { values: [1, 2] } }";
+
+    assert_eq!(debug, expected);
+}
@@ -0,0 +1,12 @@
+// Options that the derive does not understand should be rejected, pointing at
+// the offending option rather than being silently ignored.
+
+use derive_debug::CustomDebug;
+
+#[derive(CustomDebug)]
+#[debug(rename = "W", bonud = "T: std::fmt::Debug")]
+pub struct Wrapper<T> {
+    value: T,
+}
+
+fn main() {}
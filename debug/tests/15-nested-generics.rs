@@ -0,0 +1,105 @@
+// Bound inference looks at the full type of every field, not only at its last
+// path segment. A type parameter receives a `Debug` bound when it shows up
+// somewhere that will actually be formatted: inside other generic types, in
+// references, slices, arrays or tuples. It does not receive one when it only
+// appears in a position whose Debug impl does not depend on it:
+//
+//   - inside PhantomData<...>
+//   - behind a raw pointer, `*const T` and `*mut T` print the address
+//   - in a function pointer type such as `fn() -> T`
+//   - as the prefix of an associated type, which is bounded on its own
+//
+// The predicates are placed on the field types themselves, so a wrapper whose
+// Debug impl needs more than `T: Debug` works too: `Cell<T>` also requires
+// `T: Copy`, and `Peekable<I>` requires `I::Item: Debug`.
+//
+//     impl<'a, T, U, V: Trait> Debug for Nested<'a, T, U, V>
+//     where
+//         Vec<Box<T>>: Debug,
+//         Option<&'a [(T, U)]>: Debug,
+//         Option<Vec<V::Value>>: Debug,
+//         Vec<<V as Trait>::Value>: Debug,
+//     {...}
+//
+// A field that contains a generic type defined in the same crate, such as
+// `Option<Box<Two<T>>>` in test 06, may refer back to the type being derived.
+// A predicate on such a field would never resolve, so the type parameters
+// inside it are bounded instead.
+
+use derive_debug::CustomDebug;
+use std::cell::Cell;
+use std::fmt::Debug;
+use std::iter::Peekable;
+use std::marker::PhantomData;
+
+pub trait Trait {
+    type Value;
+}
+
+#[derive(CustomDebug)]
+pub struct Nested<'a, T, U, V: Trait> {
+    boxed: Vec<Box<T>>,
+    maybe: Option<&'a [(T, U)]>,
+    values: Option<Vec<V::Value>>,
+    qualified: Vec<<V as Trait>::Value>,
+    marker: PhantomData<(V, fn() -> V)>,
+}
+
+#[derive(CustomDebug)]
+pub struct Opaque<T, U> {
+    ptr: *const T,
+    callback: fn(T) -> U,
+    marker: PhantomData<Box<U>>,
+}
+
+#[derive(CustomDebug)]
+pub struct Array<T> {
+    values: [T; 4],
+}
+
+#[derive(CustomDebug)]
+pub struct Counter<T> {
+    count: Cell<T>,
+}
+
+#[derive(CustomDebug)]
+pub struct Lookahead<I: Iterator> {
+    iter: Peekable<I>,
+}
+
+fn assert_debug<F: Debug>() {}
+
+fn main() {
+    // Does not implement Debug.
+    struct NotDebug;
+
+    struct Id;
+
+    impl Trait for Id {
+        type Value = u8;
+    }
+
+    assert_debug::<Nested<u8, u16, Id>>();
+    assert_debug::<Opaque<NotDebug, NotDebug>>();
+    assert_debug::<Array<u8>>();
+    assert_debug::<Counter<u8>>();
+    assert_debug::<Lookahead<std::vec::IntoIter<u8>>>();
+
+    let n = Nested::<u8, u16, Id> {
+        boxed: vec![Box::new(1)],
+        maybe: Some(&[(2, 3)]),
+        values: Some(vec![4]),
+        qualified: vec![5],
+        marker: PhantomData,
+    };
+    let debug = format!("{:?}", n);
+    let expected = "Nested { boxed: [1], maybe: Some([(2, 3)]), values: Some([4]), qualified: [5], marker: PhantomData<";
+
+    assert!(debug.starts_with(expected));
+
+    let counter = Counter { count: Cell::new(7) };
+    assert_eq!(format!("{:?}", counter), "Counter { count: Cell { value: 7 } }");
+    let mut lookahead = Lookahead { iter: vec![1, 2].into_iter().peekable() };
+    assert_eq!(lookahead.iter.peek(), Some(&1));
+    assert!(format!("{:?}", lookahead).starts_with("Lookahead { iter: Peekable {"));
+}
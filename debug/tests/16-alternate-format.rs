@@ -0,0 +1,95 @@
+// Formatting a field through `format_args!` throws away the settings of the
+// outer Formatter, so `{:#?}` would print custom-formatted fields on a single
+// line. Fields without a custom format are handed to `debug_struct` directly,
+// and for custom formats the alternate flag is carried over at runtime:
+// a Debug placeholder like `{:x?}` becomes `{:#x?}` under `{:#?}`. Other
+// placeholders such as `{:08b}` are left alone because `#` would change what
+// they print.
+//
+// A separate format for the alternate mode can be given explicitly:
+//
+//     #[debug(format = "{}ms", alt = "{} milliseconds")]
+//
+// The bound inferred for a generic field follows the placeholders: `{:x}`
+// requires `LowerHex` instead of `Debug`, and a field with both `format` and
+// `alt` requires the traits of both.
+//
+//     impl<T> Debug for Hex<T> where T: LowerHex {...}
+//     impl<T> Debug for Both<T> where T: Display, T: LowerHex {...}
+
+use derive_debug::CustomDebug;
+use std::fmt;
+
+#[derive(CustomDebug)]
+pub struct Field {
+    name: &'static str,
+    #[debug = "0b{:08b}"]
+    bitmask: u8,
+    #[debug = "{:x?}"]
+    bytes: Vec<u8>,
+    #[debug(format = "{}ms", alt = "{} milliseconds")]
+    elapsed: u64,
+    inner: Inner,
+}
+
+#[derive(CustomDebug)]
+pub struct Inner {
+    values: (u8, u8),
+}
+
+#[derive(CustomDebug)]
+pub struct Hex<T> {
+    #[debug = "{:x}"]
+    value: T,
+}
+
+#[derive(CustomDebug)]
+pub struct Both<T> {
+    #[debug(format = "{}", alt = "{:x}")]
+    value: T,
+}
+
+// Implements LowerHex but not Debug.
+pub struct OnlyHex;
+
+impl fmt::LowerHex for OnlyHex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("beef")
+    }
+}
+
+fn main() {
+    let f = Field {
+        name: "F",
+        bitmask: 0b00011100,
+        bytes: vec![10, 255],
+        elapsed: 30,
+        inner: Inner { values: (1, 2) },
+    };
+
+    let debug = format!("{:?}", f);
+    let expected = r#"Field { name: "F", bitmask: 0b00011100, bytes: [a, ff], elapsed: 30ms, inner: Inner { values: (1, 2) } }"#;
+    assert_eq!(debug, expected);
+
+    let debug = format!("{:#?}", f);
+    let expected = r#"Field {
+    name: "F",
+    bitmask: 0b00011100,
+    bytes: [
+        0xa,
+        0xff,
+    ],
+    elapsed: 30 milliseconds,
+    inner: Inner {
+        values: (
+            1,
+            2,
+        ),
+    },
+}"#;
+    assert_eq!(debug, expected);
+
+    assert_eq!(format!("{:?}", Hex { value: OnlyHex }), "Hex { value: beef }");
+    assert_eq!(format!("{:?}", Both { value: 255u8 }), "Both { value: 255 }");
+    assert_eq!(format!("{:#?}", Both { value: 255u8 }), "Both {\n    value: ff,\n}");
+}
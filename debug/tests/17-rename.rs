@@ -0,0 +1,69 @@
+// Debug output is easier to compare against wire formats like JSON when it
+// uses the same names. Accept `#[debug(rename = "...")]` on fields in addition
+// to the struct, and `#[debug(rename_all = "...")]` on the struct with the
+// same set of rules as serde: "lowercase", "UPPERCASE", "PascalCase",
+// "camelCase", "snake_case", "SCREAMING_SNAKE_CASE", "kebab-case" and
+// "SCREAMING-KEBAB-CASE". A rename on a field takes priority over the rule.
+//
+// As in serde, `rename_all` on an enum renames the variants and leaves the
+// fields inside them alone. A rename on a variant takes priority.
+
+use derive_debug::CustomDebug;
+
+#[derive(CustomDebug)]
+#[debug(rename = "user", rename_all = "camelCase")]
+pub struct UserRecord {
+    user_id: u32,
+    display_name: &'static str,
+    #[debug(rename = "kind")]
+    r#type: &'static str,
+    #[debug = "{:?}s"]
+    last_seen_at: u64,
+}
+
+#[derive(CustomDebug)]
+#[debug(rename_all = "SCREAMING-KEBAB-CASE")]
+pub struct Header {
+    content_length: usize,
+}
+
+#[derive(CustomDebug)]
+pub struct Raw {
+    r#type: u8,
+}
+
+#[derive(CustomDebug)]
+#[debug(rename_all = "snake_case")]
+pub enum Event {
+    UserLoggedIn { user_id: u32 },
+    PageView(&'static str),
+    #[debug(rename = "bye")]
+    LoggedOut,
+}
+
+fn main() {
+    let u = UserRecord {
+        user_id: 7,
+        display_name: "ferris",
+        r#type: "admin",
+        last_seen_at: 30,
+    };
+    let debug = format!("{:?}", u);
+    let expected = r#"user { userId: 7, displayName: "ferris", kind: "admin", lastSeenAt: 30s }"#;
+    assert_eq!(debug, expected);
+
+    let h = Header { content_length: 42 };
+    let debug = format!("{:?}", h);
+    let expected = "Header { CONTENT-LENGTH: 42 }";
+    assert_eq!(debug, expected);
+
+    let r = Raw { r#type: 1 };
+    let debug = format!("{:?}", r);
+    let expected = "Raw { type: 1 }";
+    assert_eq!(debug, expected);
+
+    let e = Event::UserLoggedIn { user_id: 7 };
+    assert_eq!(format!("{:?}", e), "user_logged_in { user_id: 7 }");
+    assert_eq!(format!("{:?}", Event::PageView("/")), r#"page_view("/")"#);
+    assert_eq!(format!("{:?}", Event::LoggedOut), "bye");
+}
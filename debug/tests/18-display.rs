@@ -0,0 +1,93 @@
+// Display impls often sit right next to a CustomDebug derive. Provide a second
+// derive, CustomDisplay, driven by a template string that refers to fields by
+// name, or by index for tuple structs and tuple variants:
+//
+//     #[derive(CustomDisplay)]
+//     #[display("{name} ({id:#x})")]
+//     pub struct User {...}
+//
+// Enums take a template on every variant instead. Variants without fields may
+// leave it out, in which case the variant name is printed.
+//
+// Bounds are inferred the same way as for CustomDebug, except that the trait
+// required of each field depends on its placeholder: `{id:#x}` needs LowerHex
+// while `{name}` needs Display. Fields that the template does not mention do
+// not contribute any bound.
+//
+// A raw identifier field such as `r#type` is referenced as `{type}`, the same
+// name CustomDebug prints for it.
+//
+// Width and precision may come from other fields, as in `{name:>width$}` or
+// `{value:.prec$}`. In a tuple struct `{0:>1$}` takes the width from field 1.
+
+use derive_debug::{CustomDebug, CustomDisplay};
+use std::fmt::Display;
+
+#[derive(CustomDebug, CustomDisplay)]
+#[display("{name} ({id:#x})")]
+pub struct User<N> {
+    name: N,
+    id: u32,
+}
+
+#[derive(CustomDisplay)]
+#[display("{0}/{1:?} {{escaped}}")]
+pub struct Pair<A, B, C>(A, B, C);
+
+#[derive(CustomDisplay)]
+pub enum Event<T> {
+    #[display("login by {user}")]
+    Login { user: T, at: u64 },
+    #[display("error {0:>4}: {1}")]
+    Error(u16, &'static str),
+    Logout,
+}
+
+#[derive(CustomDisplay)]
+#[display("{type}: {name}")]
+pub struct Item {
+    r#type: &'static str,
+    name: &'static str,
+}
+
+#[derive(CustomDisplay)]
+#[display("{name:>width$}|{value:.prec$}|{width}")]
+pub struct Column {
+    name: &'static str,
+    width: usize,
+    value: f64,
+    prec: usize,
+}
+
+#[derive(CustomDisplay)]
+#[display("[{0:^1$}]")]
+pub struct Centered(&'static str, usize);
+
+fn assert_display<F: Display>() {}
+
+fn main() {
+    // Does not implement Display.
+    struct NotDisplay;
+
+    assert_display::<Pair<u8, Vec<u8>, NotDisplay>>();
+
+    let user = User { name: "ferris", id: 255 };
+    assert_eq!(user.to_string(), "ferris (0xff)");
+    assert_eq!(format!("{:?}", user), r#"User { name: "ferris", id: 255 }"#);
+
+    let pair = Pair(1, vec![2], NotDisplay);
+    assert_eq!(pair.to_string(), "1/[2] {escaped}");
+
+    let login = Event::Login { user: "root", at: 0 };
+    assert_eq!(login.to_string(), "login by root");
+    let error = Event::<&str>::Error(42, "timeout");
+    assert_eq!(error.to_string(), "error   42: timeout");
+    assert_eq!(Event::<&str>::Logout.to_string(), "Logout");
+
+    let item = Item { r#type: "fruit", name: "apple" };
+    assert_eq!(item.to_string(), "fruit: apple");
+
+    let column = Column { name: "pi", width: 4, value: 3.14159, prec: 2 };
+    assert_eq!(column.to_string(), "  pi|3.14|4");
+    assert_eq!(Centered("ab", 6).to_string(), "[  ab  ]");
+}
@@ -0,0 +1,13 @@
+// A template that mentions a field which does not exist is reported at the
+// template.
+
+use derive_debug::CustomDisplay;
+
+#[derive(CustomDisplay)]
+#[display("{name} ({identifier})")]
+pub struct User {
+    name: &'static str,
+    id: u32,
+}
+
+fn main() {}
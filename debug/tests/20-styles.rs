@@ -0,0 +1,68 @@
+// Structured loggers want `key=value` pairs rather than Rust struct syntax.
+// Accept a container attribute selecting the shape of the output:
+//
+//   - #[debug(style = "struct")] is the default, `Field { name: "F", .. }`
+//   - #[debug(style = "compact")] is the same but always a single line, even
+//     under `{:#?}`
+//   - #[debug(style = "logfmt")] prints `name=F bitmask=0b00011100`
+//
+// In logfmt values are quoted only when needed. Nested values are formatted
+// on a single line, so a nested type using the logfmt style itself ends up as
+// one quoted value, which existing logfmt tooling can still parse.
+
+use derive_debug::CustomDebug;
+
+#[derive(CustomDebug)]
+#[debug(style = "logfmt")]
+pub struct Request {
+    name: &'static str,
+    #[debug = "0b{:08b}"]
+    bitmask: u8,
+    path: String,
+    #[debug(redact)]
+    token: &'static str,
+    peer: Peer,
+}
+
+#[derive(CustomDebug)]
+#[debug(style = "logfmt")]
+pub struct Peer {
+    ip: &'static str,
+    port: u16,
+}
+
+#[derive(CustomDebug)]
+#[debug(style = "compact")]
+pub struct Point {
+    x: i32,
+    inner: Inner,
+}
+
+#[derive(CustomDebug)]
+pub struct Inner {
+    values: Vec<u8>,
+}
+
+fn main() {
+    let r = Request {
+        name: "F",
+        bitmask: 0b00011100,
+        path: "/a b".to_string(),
+        token: "secret",
+        peer: Peer {
+            ip: "127.0.0.1",
+            port: 80,
+        },
+    };
+    let expected = r#"name=F bitmask=0b00011100 path="/a b" token=[REDACTED] peer="ip=127.0.0.1 port=80""#;
+    assert_eq!(format!("{:?}", r), expected);
+    assert_eq!(format!("{:#?}", r), expected);
+
+    let p = Point {
+        x: 1,
+        inner: Inner { values: vec![2, 3] },
+    };
+    let expected = "Point { x: 1, inner: Inner { values: [2, 3] } }";
+    assert_eq!(format!("{:?}", p), expected);
+    assert_eq!(format!("{:#?}", p), expected);
+}
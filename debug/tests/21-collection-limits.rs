@@ -0,0 +1,51 @@
+// Buffers holding megabytes of data make for unreadable debug output. Accept
+// `#[debug(limit = N)]` on sequence and map fields to print only the first N
+// elements, followed by a note saying how many were left out, and
+// `#[debug(len_only)]` to print nothing but the length.
+//
+// A custom format on a limited field applies to each element, or to each value
+// in the case of a map.
+
+use derive_debug::CustomDebug;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+#[derive(CustomDebug)]
+pub struct Buffers<T> {
+    #[debug(limit = 3)]
+    samples: Vec<T>,
+    #[debug(limit = 4, format = "{:02x}")]
+    bytes: Vec<u8>,
+    #[debug(limit = 1)]
+    headers: BTreeMap<&'static str, &'static str>,
+    #[debug(limit = 8)]
+    short: [u8; 2],
+    #[debug(len_only)]
+    payload: Vec<T>,
+}
+
+fn assert_debug<F: Debug>() {}
+
+fn main() {
+    assert_debug::<Buffers<u8>>();
+
+    let mut headers = BTreeMap::new();
+    headers.insert("accept", "*/*");
+    headers.insert("host", "localhost");
+
+    let b = Buffers {
+        samples: vec![1, 2, 3, 4, 5],
+        bytes: (0..10).collect(),
+        headers,
+        short: [7, 8],
+        payload: vec![0; 1 << 20],
+    };
+
+    let debug = format!("{:?}", b);
+    let expected = concat!(
+        r#"Buffers { samples: [1, 2, 3, ... (2 more)], bytes: [00, 01, 02, 03, ... (6 more)], "#,
+        r#"headers: {"accept": "*/*", ... (1 more)}, short: [7, 8], payload: <1048576 items> }"#,
+    );
+
+    assert_eq!(debug, expected);
+}
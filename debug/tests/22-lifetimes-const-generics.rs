@@ -0,0 +1,77 @@
+// Bound inference has to cope with every kind of generic parameter, not only
+// type parameters. Lifetimes and const generics are passed through to the impl
+// untouched, defaults on parameters are dropped from the impl generics, and
+// type parameters that only appear behind PhantomData in any shape, such as
+// PhantomData<&'a T> or PhantomData<fn() -> T>, receive no bound. As in
+// test 15, the predicates are placed on the field types that use `T`.
+//
+//     impl<'a, 'b: 'a, T, U, V, const N: usize> Debug for Mixed<'a, 'b, T, U, V, N>
+//     where
+//         [T; N]: Debug,
+//         &'a [&'b T]: Debug,
+//     {...}
+
+use derive_debug::CustomDebug;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+pub trait Trait<'a> {
+    type Value;
+}
+
+#[derive(CustomDebug)]
+pub struct Mixed<'a, 'b: 'a, T, U, V = u8, const N: usize = 4> {
+    values: [T; N],
+    borrowed: &'a [&'b T],
+    by_ref: PhantomData<&'a U>,
+    by_fn: PhantomData<fn() -> V>,
+}
+
+#[derive(CustomDebug)]
+pub struct Assoc<'a, T: Trait<'a>, const N: usize> {
+    values: [T::Value; N],
+    marker: PhantomData<&'a T>,
+}
+
+#[derive(CustomDebug)]
+pub struct Defaulted<T = String> {
+    value: Option<T>,
+}
+
+fn assert_debug<F: Debug>() {}
+
+fn main() {
+    // Does not implement Debug.
+    struct NotDebug;
+
+    struct Id;
+
+    impl<'a> Trait<'a> for Id {
+        type Value = &'a str;
+    }
+
+    assert_debug::<Mixed<u8, NotDebug, NotDebug, 2>>();
+    assert_debug::<Mixed<u8, NotDebug>>();
+    assert_debug::<Assoc<Id, 3>>();
+    assert_debug::<Defaulted>();
+
+    let inner = [1, 2];
+    let m: Mixed<u8, NotDebug, NotDebug, 2> = Mixed {
+        values: [1, 2],
+        borrowed: &[&inner[0], &inner[1]],
+        by_ref: PhantomData,
+        by_fn: PhantomData,
+    };
+    let debug = format!("{:?}", m);
+    assert!(debug.starts_with("Mixed { values: [1, 2], borrowed: [1, 2], by_ref: PhantomData<&"));
+
+    let a: Assoc<Id, 2> = Assoc {
+        values: ["a", "b"],
+        marker: PhantomData,
+    };
+    let debug = format!("{:?}", a);
+    assert!(debug.starts_with(r#"Assoc { values: ["a", "b"], marker: PhantomData<&"#));
+
+    let d: Defaulted = Defaulted { value: None };
+    assert_eq!(format!("{:?}", d), "Defaulted { value: None }");
+}
@@ -0,0 +1,58 @@
+// Typestate-heavy code carries lots of marker fields that hold no data. With
+// the container attribute `#[debug(skip_markers)]`, fields of type
+// PhantomData, PhantomPinned, `()` or `[T; 0]` are left out of the output.
+// Since nothing of interest is hidden, the output does not end in `..`.
+// A marker field can still be printed by marking it `#[debug(show)]`.
+// Skipped marker fields take no part in bound inference, so `Buffer<S>`
+// implements Debug even when `S` does not.
+
+use derive_debug::CustomDebug;
+use std::fmt::Debug;
+use std::marker::{PhantomData, PhantomPinned};
+
+pub struct Open;
+
+#[derive(CustomDebug)]
+#[debug(skip_markers)]
+pub struct Connection<State> {
+    id: u32,
+    state: PhantomData<State>,
+    pinned: PhantomPinned,
+    unit: (),
+    empty: [u64; 0],
+    #[debug(show)]
+    shown: PhantomData<u8>,
+}
+
+#[derive(CustomDebug)]
+#[debug(skip_markers)]
+pub struct Buffer<S> {
+    id: u32,
+    elements: [S; 0],
+}
+
+fn assert_debug<F: Debug>() {}
+
+fn main() {
+    // Does not implement Debug.
+    struct NotDebug;
+
+    assert_debug::<Buffer<NotDebug>>();
+
+    let c = Connection::<Open> {
+        id: 1,
+        state: PhantomData,
+        pinned: PhantomPinned,
+        unit: (),
+        empty: [],
+        shown: PhantomData,
+    };
+
+    let debug = format!("{:?}", c);
+    let expected = "Connection { id: 1, shown: PhantomData<u8> }";
+
+    assert_eq!(debug, expected);
+
+    let b = Buffer::<NotDebug> { id: 2, elements: [] };
+    assert_eq!(format!("{:?}", b), "Buffer { id: 2 }");
+}
@@ -0,0 +1,44 @@
+// Fields may be gated behind `#[cfg(...)]`, for example on a cargo feature.
+// The compiler evaluates these attributes before handing the struct to the
+// derive, so a field that is configured out is simply not there: it must not
+// be referenced by the generated `.field(...)` chain, and its type must not
+// contribute to the inferred bounds. `#[cfg_attr(...)]` is expanded the same
+// way, which allows debug options to depend on configuration too.
+
+use derive_debug::CustomDebug;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+#[derive(CustomDebug)]
+#[debug(skip_markers)]
+pub struct Config<T, U> {
+    name: &'static str,
+    marker: PhantomData<T>,
+    #[cfg(any())]
+    disabled: T,
+    #[cfg(all())]
+    enabled: U,
+    #[cfg_attr(all(), debug(redact))]
+    secret: &'static str,
+}
+
+fn assert_debug<F: Debug>() {}
+
+fn main() {
+    // Does not implement Debug.
+    struct NotDebug;
+
+    assert_debug::<Config<NotDebug, u8>>();
+
+    let c = Config::<NotDebug, u8> {
+        name: "c",
+        marker: PhantomData,
+        enabled: 1,
+        secret: "hunter2",
+    };
+
+    let debug = format!("{:?}", c);
+    let expected = r#"Config { name: "c", enabled: 1, secret: [REDACTED] }"#;
+
+    assert_eq!(debug, expected);
+}
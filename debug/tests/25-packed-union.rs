@@ -0,0 +1,59 @@
+// Fields of a `#[repr(packed)]` struct may be unaligned, so they cannot be
+// borrowed for formatting. CustomDebug copies each field into a local first,
+// which requires the field types to be `Copy`; for generic packed structs the
+// inferred bounds include `Copy` as well.
+//
+// A union cannot be formatted field by field because there is no way to know
+// which field is active. It needs a container level strategy: either
+// `#[debug(opaque)]`, which prints just the name, or `#[debug(with = "...")]`,
+// which hands the whole value to a custom function.
+
+use derive_debug::CustomDebug;
+use std::fmt;
+
+#[derive(CustomDebug)]
+#[repr(C, packed)]
+pub struct Header {
+    tag: u8,
+    #[debug = "0x{:08x}"]
+    len: u32,
+}
+
+#[derive(CustomDebug)]
+#[repr(packed(2))]
+pub struct Pair<T> {
+    a: u8,
+    b: T,
+}
+
+#[derive(CustomDebug)]
+#[debug(opaque)]
+pub union Opaque {
+    int: u32,
+    float: f32,
+}
+
+#[derive(CustomDebug)]
+#[debug(with = "fmt_bits")]
+pub union Bits {
+    int: u32,
+    float: f32,
+}
+
+fn fmt_bits(bits: &Bits, fmt: &mut fmt::Formatter) -> fmt::Result {
+    write!(fmt, "Bits({:#x})", unsafe { bits.int })
+}
+
+fn main() {
+    let header = Header { tag: 7, len: 42 };
+    assert_eq!(format!("{:?}", header), "Header { tag: 7, len: 0x0000002a }");
+
+    let pair = Pair { a: 1, b: 2u64 };
+    assert_eq!(format!("{:?}", pair), "Pair { a: 1, b: 2 }");
+
+    let opaque = Opaque { float: 1.0 };
+    assert_eq!(format!("{:?}", opaque), "Opaque { .. }");
+
+    let bits = Bits { float: 1.0 };
+    assert_eq!(format!("{:?}", bits), "Bits(0x3f800000)");
+}
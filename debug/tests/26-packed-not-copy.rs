@@ -0,0 +1,14 @@
+// Every formatted field of a packed struct is copied before formatting, so a
+// field that is not `Copy` is rejected with an explanation of why the copy is
+// needed. Such a field can be skipped or redacted instead.
+
+use derive_debug::CustomDebug;
+
+#[derive(CustomDebug)]
+#[repr(packed)]
+pub struct Message {
+    id: u16,
+    body: String,
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+// A union has no way to tell which of its fields is active, so deriving
+// CustomDebug on it requires `#[debug(opaque)]` or `#[debug(with = "...")]`.
+
+use derive_debug::CustomDebug;
+
+#[derive(CustomDebug)]
+pub union Value {
+    int: u32,
+    float: f32,
+}
+
+fn main() {}
@@ -0,0 +1,72 @@
+// CustomDebug also derives for enums. Each variant prints like the std derive
+// would: unit variants as their name, tuple variants through `debug_tuple` and
+// struct variants through `debug_struct`.
+//
+// Bound inference looks at the fields of every variant, so
+//
+//     enum Event<T: Trait> { A(T::Value), B(PhantomData<T>) }
+//
+// only requires `T::Value: Debug`. Variants accept their own attributes:
+//
+//   - `#[debug(skip)]` prints just the variant name (with `..` when the variant
+//     has fields) and leaves its fields out of bound inference.
+//   - `#[debug(bound = "...")]` replaces the bounds inferred from the fields of
+//     that variant.
+//   - `#[debug(rename = "...")]` changes the printed variant name.
+
+use derive_debug::CustomDebug;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+pub trait Trait {
+    type Value;
+}
+
+#[derive(CustomDebug)]
+pub enum Event<T: Trait> {
+    A(T::Value),
+    B(PhantomData<T>),
+}
+
+#[derive(CustomDebug)]
+pub enum Message<T, U, V> {
+    Quit,
+    Move {
+        x: i32,
+        #[debug = "{:+}"]
+        y: i32,
+    },
+    Write(T, #[debug(skip)] u8),
+    #[debug(skip)]
+    Secret(U),
+    #[debug(bound = "V: Debug", rename = "Other")]
+    Custom { value: Box<V> },
+}
+
+fn assert_debug<F: Debug>() {}
+
+fn main() {
+    // Does not implement Debug, but its associated type does.
+    struct Id;
+
+    impl Trait for Id {
+        type Value = u8;
+    }
+
+    assert_debug::<Event<Id>>();
+    assert_eq!(format!("{:?}", Event::<Id>::A(7)), "A(7)");
+    assert!(format!("{:?}", Event::<Id>::B(PhantomData)).starts_with("B(PhantomData<"));
+
+    // `Id` is not Debug; the skipped variant does not require it.
+    type M = Message<String, Id, u16>;
+    assert_debug::<M>();
+    assert_eq!(format!("{:?}", M::Quit), "Quit");
+    assert_eq!(format!("{:?}", M::Move { x: 1, y: 2 }), "Move { x: 1, y: +2 }");
+    assert_eq!(format!("{:?}", M::Write("hi".to_string(), 0)), r#"Write("hi", ..)"#);
+    assert_eq!(format!("{:?}", M::Secret(Id)), "Secret(..)");
+    assert_eq!(format!("{:?}", M::Custom { value: Box::new(3) }), "Other { value: 3 }");
+    assert_eq!(
+        format!("{:#?}", M::Move { x: 1, y: 2 }),
+        "Move {\n    x: 1,\n    y: +2,\n}",
+    );
+}
@@ -0,0 +1,45 @@
+// Sometimes the interesting thing to print is computed rather than stored.
+//
+// The container attribute `#[debug(field(name = "...", expr = "..."))]`
+// appends a computed entry after the regular fields; it can be repeated. The
+// field attribute `#[debug(expr = "...")]` replaces the value of a field. Both
+// expressions can refer to the whole struct through `self`, and a field's
+// custom format applies to the value of its expression.
+//
+// Since the field's own value is never formatted, a field with `expr` does
+// not require its type to implement Debug.
+
+use derive_debug::CustomDebug;
+use std::fmt::Debug;
+
+pub struct NotDebug;
+
+#[derive(CustomDebug)]
+#[debug(field(name = "len", expr = "self.buf.len()"))]
+#[debug(field(name = "full", expr = "self.buf.len() == self.capacity"))]
+pub struct Buffer<T> {
+    #[debug(expr = "&self.buf[..self.buf.len().min(2)]")]
+    buf: Vec<u8>,
+    capacity: usize,
+    #[debug(expr = r#""<handle>""#)]
+    handle: T,
+    #[debug(expr = "self.capacity - self.buf.len()", format = "{:#x}")]
+    free: (),
+}
+
+fn assert_debug<F: Debug>() {}
+
+fn main() {
+    assert_debug::<Buffer<NotDebug>>();
+
+    let buffer = Buffer {
+        buf: vec![1, 2, 3],
+        capacity: 19,
+        handle: NotDebug,
+        free: (),
+    };
+    assert_eq!(
+        format!("{:?}", buffer),
+        r#"Buffer { buf: [1, 2], capacity: 19, handle: "<handle>", free: 0x10, len: 3, full: false }"#,
+    );
+}
@@ -0,0 +1,66 @@
+// For newtypes like `struct UserId(u64)` the wrapper usually adds nothing to
+// the output. With `#[debug(transparent)]` the Debug impl forwards to the one
+// field that is not zero-sized, so formatter flags like `{:#?}` and `{:x?}`
+// apply to the inner value directly. Marker fields such as PhantomData are
+// ignored, and only the forwarded field's type needs to implement Debug.
+//
+// Attributes on the forwarded field still apply: a redacted field prints its
+// placeholder, and a custom format or formatter function is used as usual.
+
+use derive_debug::CustomDebug;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+#[derive(CustomDebug)]
+#[debug(transparent)]
+pub struct UserId(u64);
+
+pub struct Unit;
+
+#[derive(CustomDebug)]
+#[debug(transparent)]
+pub struct Meters<T, U> {
+    value: T,
+    unit: PhantomData<U>,
+}
+
+#[derive(CustomDebug)]
+#[debug(transparent)]
+pub struct Secret(#[debug(redact)] String);
+
+#[derive(CustomDebug)]
+#[debug(transparent)]
+pub struct Token<T>(#[debug(redact = "<token>")] T);
+
+#[derive(CustomDebug)]
+#[debug(transparent)]
+pub struct Id(#[debug = "{:#x}"] u32);
+
+#[derive(CustomDebug)]
+#[debug(transparent)]
+pub struct Buffer {
+    #[debug(len_only)]
+    bytes: Vec<u8>,
+}
+
+fn assert_debug<F: Debug>() {}
+
+fn main() {
+    assert_eq!(format!("{:?}", UserId(42)), "42");
+    assert_eq!(format!("{:x?}", UserId(255)), "ff");
+
+    // `Unit` is not Debug, but it only appears in the marker field.
+    assert_debug::<Meters<f64, Unit>>();
+    let m = Meters::<_, Unit> { value: vec![1.5], unit: PhantomData };
+    assert_eq!(format!("{:?}", m), "[1.5]");
+    assert_eq!(format!("{:#?}", m), "[\n    1.5,\n]");
+
+    let secret = Secret("pw".to_owned());
+    assert_eq!(format!("{:?}", secret), "[REDACTED]");
+    assert_eq!(secret.0, "pw");
+    // The redacted field is never formatted, so it needs no Debug bound.
+    assert_debug::<Token<Unit>>();
+    assert_eq!(format!("{:?}", Token(Unit)), "<token>");
+    assert_eq!(format!("{:?}", Id(255)), "0xff");
+    assert_eq!(format!("{:?}", Buffer { bytes: vec![0; 3] }), "<3 items>");
+}
@@ -0,0 +1,13 @@
+// `#[debug(transparent)]` only makes sense when there is a single field that
+// carries data; the second one is pointed out in the error.
+
+use derive_debug::CustomDebug;
+
+#[derive(CustomDebug)]
+#[debug(transparent)]
+pub struct Point {
+    x: i32,
+    y: i32,
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+// Custom format strings are checked when the macro expands rather than inside
+// the generated `format_args!`, so mistakes are reported on the attribute
+// literal with a message about what is wrong with it. The field value is the
+// only argument: the string needs exactly one placeholder, and its spec must
+// name a real formatting trait.
+
+use derive_debug::CustomDebug;
+
+#[derive(CustomDebug)]
+pub struct Flags {
+    #[debug = "0b{:08q}"]
+    bits: u8,
+}
+
+fn main() {}
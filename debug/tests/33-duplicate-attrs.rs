@@ -0,0 +1,26 @@
+// Giving the same option twice is reported instead of silently keeping the
+// last value, at the container level as well as on fields, even when the two
+// occurrences sit in separate `#[debug(...)]` attributes.
+
+use derive_debug::CustomDebug;
+
+#[derive(CustomDebug)]
+#[debug(style = "compact", style = "logfmt")]
+pub struct Style {
+    value: u8,
+}
+
+#[derive(CustomDebug)]
+pub struct Format {
+    #[debug(format = "{:x}")]
+    #[debug(format = "{:o}")]
+    value: u8,
+}
+
+#[derive(CustomDebug)]
+pub struct Redact {
+    #[debug(redact, redact = "***")]
+    value: u8,
+}
+
+fn main() {}
@@ -0,0 +1,27 @@
+// `transparent`, `opaque` and `with` replace the field by field output, so
+// options that only shape that output would be silently ignored next to them
+// and are reported instead. `opaque` still prints the (renamed) type name.
+
+use derive_debug::CustomDebug;
+
+#[derive(CustomDebug)]
+#[debug(transparent, rename = "Wrapper")]
+pub struct Transparent(u8);
+
+#[derive(CustomDebug)]
+#[debug(opaque, rename = "Handle", style = "compact")]
+pub struct Opaque {
+    fd: i32,
+}
+
+#[derive(CustomDebug)]
+#[debug(with = "fmt_bits", field(name = "len", expr = "8"))]
+pub struct With {
+    bits: u8,
+}
+
+fn fmt_bits(with: &With, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+    write!(fmt, "{:08b}", with.bits)
+}
+
+fn main() {}
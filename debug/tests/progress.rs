@@ -9,4 +9,30 @@ fn tests() {
     t.pass("tests/06-bound-trouble.rs");
     t.pass("tests/07-associated-type.rs");
     t.pass("tests/08-escape-hatch.rs");
+    t.pass("tests/09-skip-redact.rs");
+    t.pass("tests/10-custom-formatter.rs");
+    t.pass("tests/11-field-bound.rs");
+    t.compile_fail("tests/12-malformed-bound.rs");
+    t.pass("tests/13-container-attrs.rs");
+    t.compile_fail("tests/14-unknown-container-attr.rs");
+    t.pass("tests/15-nested-generics.rs");
+    t.pass("tests/16-alternate-format.rs");
+    t.pass("tests/17-rename.rs");
+    t.pass("tests/18-display.rs");
+    t.compile_fail("tests/19-display-unknown-field.rs");
+    t.pass("tests/20-styles.rs");
+    t.pass("tests/21-collection-limits.rs");
+    t.pass("tests/22-lifetimes-const-generics.rs");
+    t.pass("tests/23-skip-markers.rs");
+    t.pass("tests/24-cfg-fields.rs");
+    t.pass("tests/25-packed-union.rs");
+    t.compile_fail("tests/26-packed-not-copy.rs");
+    t.compile_fail("tests/27-union-without-strategy.rs");
+    t.pass("tests/28-generic-enum.rs");
+    t.pass("tests/29-computed-fields.rs");
+    t.pass("tests/30-transparent.rs");
+    t.compile_fail("tests/31-transparent-two-fields.rs");
+    t.compile_fail("tests/32-invalid-format.rs");
+    t.compile_fail("tests/33-duplicate-attrs.rs");
+    t.compile_fail("tests/34-ignored-container-attrs.rs");
 }
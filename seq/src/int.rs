@@ -0,0 +1,222 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+// 范围中用到的整数。为了同时覆盖`i128`和`u128`的全部取值，能用`i128`表示的都存成`Signed`，
+// 只有超出`i128::MAX`的值才存成`Unsigned`，这样每个值只有一种表示方式
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Int {
+    Signed(i128),
+    Unsigned(u128),
+}
+
+impl Int {
+    pub(crate) fn from_u128(value: u128) -> Int {
+        match i128::try_from(value) {
+            Ok(value) => Int::Signed(value),
+            Err(_) => Int::Unsigned(value),
+        }
+    }
+
+    fn as_i128(self) -> Option<i128> {
+        match self {
+            Int::Signed(value) => Some(value),
+            Int::Unsigned(_) => None,
+        }
+    }
+
+    fn as_u128(self) -> Option<u128> {
+        match self {
+            Int::Signed(value) => u128::try_from(value).ok(),
+            Int::Unsigned(value) => Some(value),
+        }
+    }
+
+    pub(crate) fn is_negative(self) -> bool {
+        matches!(self, Int::Signed(value) if value < 0)
+    }
+
+    // 绝对值，生成字面量时负号单独输出
+    pub(crate) fn magnitude(self) -> u128 {
+        match self {
+            Int::Signed(value) => value.unsigned_abs(),
+            Int::Unsigned(value) => value,
+        }
+    }
+
+    pub(crate) fn checked_neg(self) -> Option<Int> {
+        match self {
+            Int::Signed(i128::MIN) => Some(Int::Unsigned(i128::MIN.unsigned_abs())),
+            Int::Signed(value) => Some(Int::Signed(-value)),
+            Int::Unsigned(value) if value == i128::MIN.unsigned_abs() => Some(Int::Signed(i128::MIN)),
+            Int::Unsigned(_) => None,
+        }
+    }
+
+    // 先按`i128`计算，溢出时如果两个操作数都不是负数，再按`u128`计算一次
+    pub(crate) fn checked_op(self, op: &str, rhs: Int) -> Option<Int> {
+        if let (Some(a), Some(b)) = (self.as_i128(), rhs.as_i128()) {
+            let value = match op {
+                "+" => a.checked_add(b),
+                "-" => a.checked_sub(b),
+                "*" => a.checked_mul(b),
+                "/" => a.checked_div(b),
+                "%" => a.checked_rem(b),
+                "<<" => u32::try_from(b).ok().and_then(|b| a.checked_shl(b)).filter(|v| v >> b == a),
+                ">>" => u32::try_from(b).ok().and_then(|b| a.checked_shr(b)),
+                _ => unreachable!(),
+            };
+            if let Some(value) = value {
+                return Some(Int::Signed(value));
+            }
+        }
+        let (a, b) = (self.as_u128()?, rhs.as_u128()?);
+        let value = match op {
+            "+" => a.checked_add(b),
+            "-" => a.checked_sub(b),
+            "*" => a.checked_mul(b),
+            "/" => a.checked_div(b),
+            "%" => a.checked_rem(b),
+            "<<" => u32::try_from(b).ok().and_then(|b| a.checked_shl(b)).filter(|v| v >> b == a),
+            ">>" => u32::try_from(b).ok().and_then(|b| a.checked_shr(b)),
+            _ => unreachable!(),
+        };
+        value.map(Int::from_u128)
+    }
+
+    // 判断值是否在字面量后缀对应类型的取值范围内，`usize`和`isize`的范围取决于目标平台，交给编译器检查
+    pub(crate) fn fits_suffix(self, suffix: &str) -> bool {
+        let (min, max): (i128, u128) = match suffix {
+            "u8" => (0, u8::MAX.into()),
+            "u16" => (0, u16::MAX.into()),
+            "u32" => (0, u32::MAX.into()),
+            "u64" => (0, u64::MAX.into()),
+            "u128" => (0, u128::MAX),
+            "i8" => (i8::MIN.into(), i8::MAX as u128),
+            "i16" => (i16::MIN.into(), i16::MAX as u128),
+            "i32" => (i32::MIN.into(), i32::MAX as u128),
+            "i64" => (i64::MIN.into(), i64::MAX as u128),
+            "i128" => (i128::MIN, i128::MAX as u128),
+            _ => return true,
+        };
+        match self {
+            Int::Signed(value) => value >= min && (value < 0 || value as u128 <= max),
+            Int::Unsigned(value) => value <= max,
+        }
+    }
+}
+
+impl Ord for Int {
+    fn cmp(&self, other: &Int) -> Ordering {
+        match (*self, *other) {
+            (Int::Signed(a), Int::Signed(b)) => a.cmp(&b),
+            (Int::Unsigned(a), Int::Unsigned(b)) => a.cmp(&b),
+            (Int::Signed(_), Int::Unsigned(_)) => Ordering::Less,
+            (Int::Unsigned(_), Int::Signed(_)) => Ordering::Greater,
+        }
+    }
+}
+
+impl PartialOrd for Int {
+    fn partial_cmp(&self, other: &Int) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Int {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Int::Signed(value) => write!(f, "{}", value),
+            Int::Unsigned(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+// 在宏里计算由整数字面量和`+ - * / % << >>`组成的常量表达式，运算符的优先级与Rust相同。
+// 宏看不到常量或者变量的值，遇到字面量以外的操作数时报错，错误指向这个操作数。
+// 字面量上的类型后缀（例如`0u8`）记录到`suffix`中，同一个表达式中的后缀必须一致
+pub(crate) fn parse_const_expr(input: syn::parse::ParseStream, suffix: &mut Option<String>) -> syn::Result<Int> {
+    parse_binary_expr(input, 0, suffix)
+}
+
+// 优先级爬升：只处理优先级不低于`min_prec`的运算符，右侧操作数用更高的优先级递归解析，保证左结合
+fn parse_binary_expr(input: syn::parse::ParseStream, min_prec: u8, suffix: &mut Option<String>) -> syn::Result<Int> {
+    let mut lhs = parse_operand(input, suffix)?;
+    while let Some((op, prec)) = peek_binary_op(input) {
+        if prec < min_prec {
+            break;
+        }
+        let span = input.span();
+        // 运算符由一个或两个标点符号组成，逐个消耗掉
+        for _ in 0..op.len() {
+            input.parse::<proc_macro2::Punct>()?;
+        }
+        let rhs = parse_binary_expr(input, prec + 1, suffix)?;
+        if (op == "/" || op == "%") && rhs == Int::Signed(0) {
+            return Err(syn::Error::new(span, "attempt to divide by zero"));
+        }
+        lhs = lhs
+            .checked_op(op, rhs)
+            .ok_or_else(|| syn::Error::new(span, format!("arithmetic overflow in `{} {} {}`", lhs, op, rhs)))?;
+    }
+    Ok(lhs)
+}
+
+fn peek_binary_op(input: syn::parse::ParseStream) -> Option<(&'static str, u8)> {
+    if input.peek(syn::Token!(*)) {
+        Some(("*", 3))
+    } else if input.peek(syn::Token!(/)) {
+        Some(("/", 3))
+    } else if input.peek(syn::Token!(%)) {
+        Some(("%", 3))
+    } else if input.peek(syn::Token!(+)) {
+        Some(("+", 2))
+    } else if input.peek(syn::Token!(-)) {
+        Some(("-", 2))
+    } else if input.peek(syn::Token!(<<)) {
+        Some(("<<", 1))
+    } else if input.peek(syn::Token!(>>)) {
+        Some((">>", 1))
+    } else {
+        None
+    }
+}
+
+// 操作数可以是整数字面量、带负号的操作数或者圆括号括起来的表达式
+fn parse_operand(input: syn::parse::ParseStream, suffix: &mut Option<String>) -> syn::Result<Int> {
+    if input.peek(syn::Token!(-)) {
+        let minus = input.parse::<syn::Token!(-)>()?;
+        let value = parse_operand(input, suffix)?;
+        return value
+            .checked_neg()
+            .ok_or_else(|| syn::Error::new(minus.span, format!("arithmetic overflow in `-{}`", value)));
+    }
+    if input.peek(syn::token::Paren) {
+        let content;
+        syn::parenthesized!(content in input);
+        let value = parse_const_expr(&content, suffix)?;
+        if !content.is_empty() {
+            return Err(content.error("expected an operator or `)`"));
+        }
+        return Ok(value);
+    }
+    if input.peek(syn::LitInt) {
+        let lit = input.parse::<syn::LitInt>()?;
+        if !lit.suffix().is_empty() {
+            match suffix {
+                Some(ref existing) if existing != lit.suffix() => {
+                    return Err(syn::Error::new(
+                        lit.span(),
+                        format!("mismatched integer suffixes `{}` and `{}`", existing, lit.suffix()),
+                    ));
+                }
+                _ => *suffix = Some(lit.suffix().to_string()),
+            }
+        }
+        return Ok(Int::from_u128(lit.base10_parse()?));
+    }
+    let operand = input.parse::<proc_macro2::TokenTree>()?;
+    Err(syn::Error::new(
+        operand.span(),
+        "expected an integer literal, `seq!` evaluates range bounds in the macro and cannot use the value of constants or variables",
+    ))
+}
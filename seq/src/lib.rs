@@ -1,33 +1,122 @@
-use proc_macro::{TokenStream};
-use proc_macro2;
-use quote;
-use syn;
+use proc_macro::TokenStream;
+use syn::ext::IdentExt;
+
+use int::{parse_const_expr, Int};
+
+mod int;
 
 #[proc_macro]
 pub fn seq(input: TokenStream) -> TokenStream {
     // eprintln!("{:#?}", input);
     let st = syn::parse_macro_input!(input as SeqParser);
+    match st.do_expand() {
+        Ok(token_stream) => token_stream.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+struct SeqParser {
+    loops: Vec<SeqLoop>, // `(I, J) in 0..4, 0..4`这样的写法会有多个变量，按照它们的笛卡尔积展开
+    body: proc_macro2::TokenStream,
+}
+
+// 一个变量以及它要依次取的值
+struct SeqLoop {
+    variable_ident: syn::Ident,
+    index_ident: Option<syn::Ident>, // `index I`子句给出的变量，取值为当前是第几次重复（从0开始）
+    values: Vec<SeqValue>,           // 按照范围（或者列表）、步长和方向算出的变量依次要取的值
+    suffix: String,                  // 范围的字面量上带的类型后缀，例如`0u8..4u8`中的`u8`，生成的每个字面量都带上它
+}
+
+// 展开时的一个变量以及它当前的取值
+#[derive(Clone)]
+struct Binding<'a> {
+    ident: &'a syn::Ident,
+    value: SeqValue,
+    suffix: &'a str,
+}
+
+// 变量可以取整数、字符（`'a'..='z'`）、字节（`b'0'..=b'9'`），或者列表（`[u8, u16]`）中的一项
+#[derive(Clone)]
+enum SeqValue {
+    Int(Int),
+    Char(char),
+    Byte(u8),
+    Item(ListItem),
+}
 
-    let mut ret = proc_macro2::TokenStream::new();
+// 列表中的一项，按照类型或者表达式解析
+#[derive(Clone)]
+enum ListItem {
+    Type(Box<syn::Type>),
+    Expr(Box<syn::Expr>),
+}
 
-    let buffer = syn::buffer::TokenBuffer::new2(st.body.clone());
-    let (ret_1, expanded) = st.find_block_to_expand_and_do_expand(buffer.begin());
-    // eprintln!("{:?}", expanded);
-    if expanded {
-        return ret_1.into();
+impl ListItem {
+    // 替换变量时，`1 + 2`、`-1`这样的表达式要用圆括号括起来，`X * 2`才会先算出`X`的值，
+    // 和负数字面量的处理方式一样。类型放在不可见的分组中，`&T`中的`T`为`dyn A + B`时也能作为一个整体。
+    // 字面量、路径、方法调用等本身就是一个整体的表达式原样输出，避免生成多余的括号
+    fn to_grouped_tokens(&self, span: proc_macro2::Span) -> proc_macro2::TokenStream {
+        let delimiter = match *self {
+            ListItem::Type(_) => proc_macro2::Delimiter::None,
+            ListItem::Expr(ref expr) => match **expr {
+                syn::Expr::Lit(_)
+                | syn::Expr::Path(_)
+                | syn::Expr::Paren(_)
+                | syn::Expr::Tuple(_)
+                | syn::Expr::Array(_)
+                | syn::Expr::Call(_)
+                | syn::Expr::MethodCall(_)
+                | syn::Expr::Field(_)
+                | syn::Expr::Index(_)
+                | syn::Expr::Macro(_)
+                | syn::Expr::Struct(_)
+                | syn::Expr::Block(_) => return quote::quote!(#self),
+                _ => proc_macro2::Delimiter::Parenthesis,
+            },
+        };
+        let mut group = proc_macro2::Group::new(delimiter, quote::quote!(#self));
+        group.set_span(span);
+        quote::quote!(#group)
     }
+}
 
-    for i in st.start..st.end {
-        ret.extend(st.expand(&st.body, i))
+impl quote::ToTokens for ListItem {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        match *self {
+            ListItem::Type(ref ty) => ty.to_tokens(tokens),
+            ListItem::Expr(ref expr) => expr.to_tokens(tokens),
+        }
     }
-    return ret.into();
 }
 
-struct SeqParser {
-    variable_ident: syn::Ident,
-    start: isize,
-    end: isize,
-    body: proc_macro2::TokenStream,
+impl SeqValue {
+    // 替换变量时生成的字面量，整数带上范围中给出的类型后缀
+    fn to_tokens(&self, suffix: &str, span: proc_macro2::Span) -> proc_macro2::TokenStream {
+        match *self {
+            SeqValue::Int(n) => make_int_literal(n, suffix, span),
+            SeqValue::Char(c) => {
+                let lit = syn::LitChar::new(c, span);
+                quote::quote!(#lit)
+            }
+            SeqValue::Byte(b) => {
+                let lit = syn::LitByte::new(b, span);
+                quote::quote!(#lit)
+            }
+            // 列表中的一项作为一个整体输出，不会和周围的运算符结合
+            SeqValue::Item(ref item) => item.to_grouped_tokens(span),
+        }
+    }
+
+    // 拼接到标识符中的文本，字符和字节拼接的是字符本身
+    fn to_ident_fragment(&self) -> String {
+        match *self {
+            SeqValue::Int(n) => n.to_string(),
+            SeqValue::Char(c) => c.to_string(),
+            SeqValue::Byte(b) => char::from(b).to_string(),
+            SeqValue::Item(ref item) => quote::quote!(#item).to_string(),
+        }
+    }
 }
 
 impl syn::parse::Parse for SeqParser {
@@ -35,12 +124,53 @@ impl syn::parse::Parse for SeqParser {
         // 我们要解析形 如 `N in 0..512 { ... }` 这样的代码片段
         // 假定`ParseStream` 当前游标对应的是一个可以解析为 `Ident` 类型的Token.
         // 如果是 `Ident` 类型节点，则返回Ok并将当前读取游标向后移动一个Token
-        // 如果不是 `Ident` 类型，则返回 Err，说明语法错误 
-        let variable_ident = input.parse::<syn::Ident>()?;
+        // 如果不是 `Ident` 类型，则返回 Err，说明语法错误
+        // 多个变量写成`(I, J) in 0..4, 0..4`的形式，每个变量对应一个用逗号分隔的范围
+        let variable_idents: Vec<syn::Ident> = if input.peek(syn::token::Paren) {
+            let content;
+            let paren = syn::parenthesized!(content in input);
+            let idents = syn::punctuated::Punctuated::<syn::Ident, syn::Token!(,)>::parse_terminated(&content)?;
+            if idents.is_empty() {
+                return Err(syn::Error::new(paren.span, "expected at least one variable"));
+            }
+            idents.into_iter().collect()
+        } else {
+            vec![input.parse::<syn::Ident>()?]
+        };
         // 假定`ParseStream`当前游标对应的是一个写作`in`的自定义的Token
         input.parse::<syn::Token!(in)>()?;
-        // 假定`ParseStream`当前游标对应的是一个可以解析为整形字面量的Token.
-        let start = input.parse::<syn::LitInt>()?;
+
+        let mut loops = Vec::new();
+        for (idx, variable_ident) in variable_idents.into_iter().enumerate() {
+            if idx > 0 {
+                input.parse::<syn::Token!(,)>()?;
+            }
+            loops.push(parse_seq_loop(input, variable_ident)?);
+        }
+
+        // 这里展示了 braced! 宏的用法，用于把一个代码块整体读取出来，如果读取成功就将代码块
+        // 内部数据作为一个 `ParseBuffer` 类型的数据返回，同时把读取游标移动到整个代码块的后面
+        let body_buf;
+        syn::braced!(body_buf in input);
+        let body: proc_macro2::TokenStream = body_buf.parse()?;
+
+        Ok(SeqParser { loops, body })
+    }
+}
+
+// 解析一个变量的取值来源，以及跟在后面的`step`、`rev`、`index`子句
+fn parse_seq_loop(input: syn::parse::ParseStream, variable_ident: syn::Ident) -> syn::Result<SeqLoop> {
+    // 变量可以在一个方括号括起来的列表上迭代，例如`[u8, u16, u32]`，
+    // 否则是一个范围，范围的两端可以是整数字面量，也可以是由字面量组成的常量表达式，例如`(1 << 4) * 2`，
+    // 或者是字符、字节字面量
+    let mut suffix = None;
+    let range = if input.peek(syn::token::Bracket) {
+        let content;
+        syn::bracketed!(content in input);
+        SeqRange::List(parse_list_items(&content)?)
+    } else {
+        let start_span = input.span();
+        let start = parse_range_bound(input, &mut suffix)?;
         // 假定`ParseStream`当前游标对应的是一个写作`..`的自定义的Token
         input.parse::<syn::Token!(..)>()?;
 
@@ -50,76 +180,304 @@ impl syn::parse::Parse for SeqParser {
             inc = true;
         }
 
-        // 假定`ParseStream`当前游标对应的是一个可以解析为整形数字面量的 Token，
-        let end = input.parse::<syn::LitInt>()?;
+        let end_span = input.span();
+        let end = parse_range_bound(input, &mut suffix)?;
+        SeqRange::Bounds { start, end, inc, start_span, end_span }
+    };
 
-        // 这里展示了 braced! 宏的用法，用于把一个代码块整体读取出来，如果读取成功就将代码块
-        // 内部数据作为一个 `ParseBuffer` 类型的数据返回，同时把读取游标移动到整个代码块的后面
-        let body_buf;
-        syn::braced!(body_buf in input);
-        let body: proc_macro2::TokenStream = body_buf.parse()?;
+    // 后面可以跟着`step K`（或者`by K`）、`rev`以及`index I`，顺序不限，每个最多出现一次
+    let mut step = None;
+    let mut rev = false;
+    let mut index_ident = None;
+    while input.peek(syn::Ident) {
+        let keyword = input.parse::<syn::Ident>()?;
+        if (keyword == "step" || keyword == "by") && step.is_none() {
+            let span = input.span();
+            // 步长上的后缀不影响生成的字面量
+            let value = parse_const_expr(input, &mut None)?;
+            if value <= Int::Signed(0) {
+                return Err(syn::Error::new(span, "step must be a positive integer"));
+            }
+            step = Some(value);
+        } else if keyword == "rev" && !rev {
+            rev = true;
+        } else if keyword == "index" && index_ident.is_none() {
+            index_ident = Some(input.parse::<syn::Ident>()?);
+        } else {
+            return Err(syn::Error::new(keyword.span(), "expected `step`, `by`, `rev`, `index`, `,` or a `{ ... }` body"));
+        }
+    }
 
-        let mut t = SeqParser {
-            variable_ident,
-            start: start.base10_parse()?,
-            end: end.base10_parse()?,
-            body,
-        };
-        if inc {
-            t.end += 1;
+    let step = step.unwrap_or(Int::Signed(1));
+    let step_usize = usize::try_from(step.magnitude()).unwrap_or(usize::MAX);
+    let suffix = suffix.unwrap_or_default();
+    let mut values: Vec<SeqValue> = match range {
+        SeqRange::List(items) => items.into_iter().step_by(step_usize).map(SeqValue::Item).collect(),
+        SeqRange::Bounds { start, end, inc, start_span, end_span } => match (start, end) {
+            (SeqValue::Int(start), SeqValue::Int(end)) => {
+                let values = int_range_values(start, end, inc, step);
+                // 带后缀的字面量超出类型的范围时在这里报错，而不是在生成的代码里
+                if let (Some(first), Some(last)) = (values.first(), values.last()) {
+                    for (value, span) in [(first, start_span), (last, end_span)] {
+                        if !value.fits_suffix(&suffix) {
+                            return Err(syn::Error::new(span, format!("value {} is out of range for `{}`", value, suffix)));
+                        }
+                    }
+                }
+                values.into_iter().map(SeqValue::Int).collect()
+            }
+            // 字符和字节直接使用标准库的区间迭代，字符区间会自动跳过代理码点
+            (SeqValue::Char(start), SeqValue::Char(end)) => {
+                if inc {
+                    (start..=end).step_by(step_usize).map(SeqValue::Char).collect()
+                } else {
+                    (start..end).step_by(step_usize).map(SeqValue::Char).collect()
+                }
+            }
+            (SeqValue::Byte(start), SeqValue::Byte(end)) => {
+                if inc {
+                    (start..=end).step_by(step_usize).map(SeqValue::Byte).collect()
+                } else {
+                    (start..end).step_by(step_usize).map(SeqValue::Byte).collect()
+                }
+            }
+            _ => {
+                return Err(syn::Error::new(end_span, "both ends of the range must be integers, characters or bytes alike"));
+            }
+        },
+    };
+    if rev {
+        values.reverse();
+    }
+
+    Ok(SeqLoop {
+        variable_ident,
+        index_ident,
+        values,
+        suffix,
+    })
+}
+
+// 变量的取值来源：一个范围，或者一个列表
+enum SeqRange {
+    Bounds {
+        start: SeqValue,
+        end: SeqValue,
+        inc: bool,
+        start_span: proc_macro2::Span,
+        end_span: proc_macro2::Span,
+    },
+    List(Vec<ListItem>),
+}
+
+// 把列表按照逗号拆成多项。每一项先尝试按类型解析（`u8`、`HashMap<fn() -> u8, u16>`），
+// 后面紧跟着`,`或者列表结尾时就是一个类型，否则按表达式解析（`"alpha"`、`1 + 2`）。
+// 泛型参数和表达式内部的逗号由syn的解析器处理，允许列表末尾有多余的逗号
+fn parse_list_items(input: syn::parse::ParseStream) -> syn::Result<Vec<ListItem>> {
+    let mut items = Vec::new();
+    while !input.is_empty() {
+        let fork = input.fork();
+        let is_type = fork.parse::<syn::Type>().is_ok() && (fork.is_empty() || fork.peek(syn::Token!(,)));
+        if is_type {
+            items.push(ListItem::Type(input.parse()?));
+        } else {
+            items.push(ListItem::Expr(input.parse()?));
+        }
+        if input.is_empty() {
+            break;
+        }
+        input.parse::<syn::Token!(,)>()?;
+    }
+    Ok(items)
+}
+
+// 范围的一端：字符字面量、字节字面量，或者整数常量表达式
+fn parse_range_bound(input: syn::parse::ParseStream, suffix: &mut Option<String>) -> syn::Result<SeqValue> {
+    if input.peek(syn::LitChar) {
+        return Ok(SeqValue::Char(input.parse::<syn::LitChar>()?.value()));
+    }
+    if input.peek(syn::LitByte) {
+        return Ok(SeqValue::Byte(input.parse::<syn::LitByte>()?.value()));
+    }
+    Ok(SeqValue::Int(parse_const_expr(input, suffix)?))
+}
+
+// 逐个累加步长算出所有的值，闭区间不需要先把终点加一，也就不会在类型的最大值上溢出
+fn int_range_values(start: Int, end: Int, inc: bool, step: Int) -> Vec<Int> {
+    let mut values = Vec::new();
+    let mut value = start;
+    while value < end || (inc && value == end) {
+        values.push(value);
+        match value.checked_op("+", step) {
+            Some(next) => value = next,
+            None => break,
         }
-        Ok(t)
     }
+    values
+}
+
+impl SeqLoop {
+    // 第`i`次重复时这个变量（以及`index`变量）的取值
+    fn bindings(&self, i: usize) -> Vec<Binding<'_>> {
+        let mut bindings = vec![Binding {
+            ident: &self.variable_ident,
+            value: self.values[i].clone(),
+            suffix: &self.suffix,
+        }];
+        if let Some(ref index_ident) = self.index_ident {
+            bindings.push(Binding {
+                ident: index_ident,
+                value: SeqValue::Int(Int::from_u128(i as u128)),
+                suffix: "",
+            });
+        }
+        bindings
+    }
+}
+
+// 多个变量取值的笛卡尔积，排在前面的变量变化得慢，和嵌套的`for`循环顺序一致
+fn cartesian_bindings(loops: &[SeqLoop]) -> Vec<Vec<Binding<'_>>> {
+    let mut combinations = vec![Vec::new()];
+    for seq_loop in loops {
+        let mut next = Vec::new();
+        for combination in &combinations {
+            for i in 0..seq_loop.values.len() {
+                let mut bindings: Vec<Binding> = combination.clone();
+                bindings.extend(seq_loop.bindings(i));
+                next.push(bindings);
+            }
+        }
+        combinations = next;
+    }
+    combinations
+}
+
+// 判断`buf[idx..]`是否以`seq ! { ... }`这样的嵌套宏调用开头
+fn is_nested_seq(buf: &[proc_macro2::TokenTree], idx: usize) -> bool {
+    match (buf.get(idx), buf.get(idx + 1), buf.get(idx + 2)) {
+        (
+            Some(proc_macro2::TokenTree::Ident(i)),
+            Some(proc_macro2::TokenTree::Punct(p)),
+            Some(proc_macro2::TokenTree::Group(_)),
+        ) => i == "seq" && p.as_char() == '!',
+        _ => false,
+    }
+}
+
+// 嵌套的`seq!`自己声明的变量，写作`N in ...`或者`(I, J) in ...`
+fn nested_seq_variables(ts: &proc_macro2::TokenStream) -> Vec<proc_macro2::Ident> {
+    match ts.clone().into_iter().next() {
+        Some(proc_macro2::TokenTree::Ident(i)) => vec![i],
+        Some(proc_macro2::TokenTree::Group(g)) if g.delimiter() == proc_macro2::Delimiter::Parenthesis => g
+            .stream()
+            .into_iter()
+            .filter_map(|tt| match tt {
+                proc_macro2::TokenTree::Ident(i) => Some(i),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// 判断一段代码中是否还有`#(...)*`，嵌套的`seq!`中的不算
+fn contains_repeat_section(ts: &proc_macro2::TokenStream) -> bool {
+    let buf = ts.clone().into_iter().collect::<Vec<_>>();
+    let mut idx = 0;
+    while idx < buf.len() {
+        if is_nested_seq(&buf, idx) {
+            idx += 3;
+            continue;
+        }
+        match (&buf[idx], buf.get(idx + 1), buf.get(idx + 2)) {
+            (
+                proc_macro2::TokenTree::Punct(prefix),
+                Some(proc_macro2::TokenTree::Group(g)),
+                Some(proc_macro2::TokenTree::Punct(suffix)),
+            ) if prefix.as_char() == '#' && g.delimiter() == proc_macro2::Delimiter::Parenthesis && suffix.as_char() == '*' => {
+                return true;
+            }
+            (proc_macro2::TokenTree::Group(g), _, _) if contains_repeat_section(&g.stream()) => return true,
+            _ => {}
+        }
+        idx += 1;
+    }
+    false
 }
 
 impl SeqParser {
-    fn expand(&self, ts: &proc_macro2::TokenStream, n: isize) -> proc_macro2::TokenStream {
+    fn do_expand(&self) -> syn::Result<proc_macro2::TokenStream> {
+        let buffer = syn::buffer::TokenBuffer::new2(self.body.clone());
+        let (ret, expanded) = self.find_block_to_expand_and_do_expand(buffer.begin(), 0)?;
+        // eprintln!("{:?}", expanded);
+        if expanded {
+            return Ok(ret);
+        }
+
+        // 没有`#(...)*`时整个代码块按照所有变量的笛卡尔积重复
+        let mut ret = proc_macro2::TokenStream::new();
+        for bindings in cartesian_bindings(&self.loops) {
+            ret.extend(self.expand(&self.body, &bindings, &[])?)
+        }
+        Ok(ret)
+    }
+
+    // `shadowed`是外层嵌套的`seq!`中声明的变量，它们不参与这一层的拼接
+    fn expand(
+        &self,
+        ts: &proc_macro2::TokenStream,
+        bindings: &[Binding],
+        shadowed: &[proc_macro2::Ident],
+    ) -> syn::Result<proc_macro2::TokenStream> {
         let buf = ts.clone().into_iter().collect::<Vec<_>>();
         let mut ret = proc_macro2::TokenStream::new();
 
         let mut idx = 0;
-         while idx < buf.len() {
+        while idx < buf.len() {
             let tree_node = &buf[idx];
+            // 嵌套的`seq!`里，和它自己的变量同名的标识符归内层管，这里只替换其他变量
+            if is_nested_seq(&buf, idx) {
+                if let proc_macro2::TokenTree::Group(g) = &buf[idx + 2] {
+                    let nested_variables = nested_seq_variables(&g.stream());
+                    let visible = bindings
+                        .iter()
+                        .filter(|b| !nested_variables.iter().any(|s| b.ident == s))
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    let shadowed = [shadowed, &nested_variables].concat();
+                    let new_stream = self.expand(&g.stream(), &visible, &shadowed)?;
+                    let mut wrap_in_group = proc_macro2::Group::new(g.delimiter(), new_stream);
+                    wrap_in_group.set_span(g.span());
+                    let (seq_ident, bang) = (&buf[idx], &buf[idx + 1]);
+                    ret.extend(quote::quote! {#seq_ident #bang #wrap_in_group});
+                    idx += 3;
+                    continue;
+                }
+            }
             match tree_node {
                 proc_macro2::TokenTree::Group(g) => {
                     // 如果是括号包含的内容，我们就递归处理内部的TokenStream
-                    let new_stream = self.expand(&g.stream(), n);
+                    let new_stream = self.expand(&g.stream(), bindings, shadowed)?;
                     // 这里需要注意，g.stream() 返回的是Group内部的TokenStream.
                     let wrap_in_group = proc_macro2::Group::new(g.delimiter(), new_stream);
                     ret.extend(quote::quote! {#wrap_in_group});
                 }
                 proc_macro2::TokenTree::Ident(prefix) => {
-                    if idx + 2 < buf.len() { // 我们需要向后预读两个TokenTree元素
-                        if let proc_macro2::TokenTree::Punct(p) = &buf[idx+1] {
-                            if p.as_char() == '#' {
-                                if let proc_macro2::TokenTree::Ident(i) = &buf[idx+2] {
-                                    if i == &self.variable_ident // 校验是否连续，无空格
-                                    && prefix.span().end() == p.span().start()
-                                    && p.span().end() == i.span().start()
-                                    {
-                                        let new_ident_litral = format!("{}{}", prefix.to_string(), n);
-                                        let new_ident = proc_macro2::Ident::new(new_ident_litral.as_str(), prefix.span());
-                                        ret.extend(quote::quote!(#new_ident));
-                                        idx += 3;
-                                        continue;
-                                    }
-
-                                }
-                            }
-                        }
-
+                    // 向后预读，看看是否是`f~N`、`f~N~_impl`、`C~_reg`、`reg~N:02`这样的标识符拼接
+                    if let Some((new_ident, consumed)) = paste_ident(&buf, idx, bindings, shadowed)? {
+                        ret.extend(quote::quote!(#new_ident));
+                        idx += consumed;
+                        continue;
                     }
 
-
                     // 如果是一个 Ident，那么看一下是否为要替换的变量标识符，如果是则替换，如果不是则透传
-                    if prefix == &self.variable_ident {
-                        let new_ident = proc_macro2::Literal::i64_unsuffixed(n as i64);
-                        ret.extend(quote::quote! {#new_ident});
+                    if let Some(binding) = bindings.iter().find(|b| b.ident == prefix) {
+                        ret.extend(binding.value.to_tokens(binding.suffix, prefix.span()));
                         idx += 1;
                         continue;
-                    } 
+                    }
                     ret.extend(quote::quote! {#tree_node});
-                    
+
                 }
                 _ => {
                     // 对于其他的元素（也就是Punct和Literal），原封不动传递
@@ -129,13 +487,16 @@ impl SeqParser {
             idx += 1;
         }
 
-        ret
+        Ok(ret)
     }
 
-    fn find_block_to_expand_and_do_expand(&self, c: syn::buffer::Cursor) -> (proc_macro2::TokenStream, bool) {
+    // 在`depth`层找到的`#(...)*`由第`depth`个变量负责展开。
+    // 如果里面还嵌套了`#(...)*`，先代入这一层变量的值，再由后面的变量展开内层；
+    // 否则这一段按照剩下所有变量的笛卡尔积重复
+    fn find_block_to_expand_and_do_expand(&self, c: syn::buffer::Cursor, depth: usize) -> syn::Result<(proc_macro2::TokenStream, bool)> {
         let mut found = false;
         let mut ret = proc_macro2::TokenStream::new();
-    
+
         let mut cursor = c;
         while !cursor.eof() {
             // 注意punct()这个函数的返回值，它返回一个新的 `Cursor` 类型的值
@@ -148,35 +509,71 @@ impl SeqParser {
                         if let Some((punct_suffix, cursor_3)) = cursor_2.punct() {
                             if punct_suffix.as_char() == '*' {
                                 // 找到了匹配的模式，按照指定的次数开始展开
-                                for i in self.start..self.end {
-                                    let t = self.expand(&group_cur.token_stream(), i);
-                                    ret.extend(t);
+                                if depth >= self.loops.len() {
+                                    return Err(syn::Error::new(
+                                        punct_prefix.span(),
+                                        "no `seq!` variable is left for this nested `#(...)*`, declare one more variable",
+                                    ));
+                                }
+                                let content = group_cur.token_stream();
+                                if contains_repeat_section(&content) {
+                                    let seq_loop = &self.loops[depth];
+                                    // 后面的变量由内层的`#(...)*`代入，这一层拼接时在它们前面停下来
+                                    let inner_variables = self.loops[depth + 1..]
+                                        .iter()
+                                        .flat_map(|l| std::iter::once(&l.variable_ident).chain(l.index_ident.as_ref()))
+                                        .cloned()
+                                        .collect::<Vec<_>>();
+                                    for i in 0..seq_loop.values.len() {
+                                        let t = self.expand(&content, &seq_loop.bindings(i), &inner_variables)?;
+                                        let inner = syn::buffer::TokenBuffer::new2(t);
+                                        let (t, _) = self.find_block_to_expand_and_do_expand(inner.begin(), depth + 1)?;
+                                        ret.extend(t);
+                                    }
+                                } else {
+                                    for bindings in cartesian_bindings(&self.loops[depth..]) {
+                                        ret.extend(self.expand(&content, &bindings, &[])?);
+                                    }
                                 }
                                 cursor = cursor_3;
                                 found = true;
                                 continue;
                             }
                         }
-                        
+
                     }
-                }   
+                }
             }
-    
+
+            // 嵌套的`seq!`中的`#(...)*`由内层的宏自己展开，原样保留
+            if let Some((ident, cursor_1)) = cursor.ident() {
+                if ident == "seq" {
+                    if let Some((bang, cursor_2)) = cursor_1.punct().filter(|(p, _)| p.as_char() == '!') {
+                        if let Some((group, next_cur)) = cursor_2.token_tree().filter(|(tt, _)| matches!(tt, proc_macro2::TokenTree::Group(_))) {
+                            ret.extend(quote::quote!(#ident #bang #group));
+                            cursor = next_cur;
+                            continue;
+                        }
+                    }
+                }
+            }
+
+            // 多个`#(...)*`分布在不同的括号里时，只要有一处被展开了，整个代码块就不再整体重复
             if let Some((group_cur,_,next_cur)) = cursor.group(proc_macro2::Delimiter::Brace) {
-                let (t, f) = self.find_block_to_expand_and_do_expand(group_cur);
-                found = f;
+                let (t, f) = self.find_block_to_expand_and_do_expand(group_cur, depth)?;
+                found |= f;
                 ret.extend(quote::quote!({#t}));
                 cursor = next_cur;
                 continue;
             } else if let Some((group_cur,_,next_cur)) = cursor.group(proc_macro2::Delimiter::Bracket) {
-                let (t, f) = self.find_block_to_expand_and_do_expand(group_cur);
-                found = f;
+                let (t, f) = self.find_block_to_expand_and_do_expand(group_cur, depth)?;
+                found |= f;
                 ret.extend(quote::quote!([#t]));
                 cursor = next_cur;
                 continue;
             } else if let Some((group_cur,_,next_cur)) = cursor.group(proc_macro2::Delimiter::Parenthesis) {
-                let (t, f) = self.find_block_to_expand_and_do_expand(group_cur);
-                found = f;
+                let (t, f) = self.find_block_to_expand_and_do_expand(group_cur, depth)?;
+                found |= f;
                 ret.extend(quote::quote!((#t)));
                 cursor = next_cur;
                 continue;
@@ -198,6 +595,155 @@ impl SeqParser {
                 continue;
             }
         }
-        (ret, found)
+        Ok((ret, found))
+    }
+}
+
+// 从`buf[idx]`开始，把用`#`或者`~`连接起来的若干个标识符拼接成一个标识符，
+// 返回拼接结果和消耗掉的Token数量。其中至少要有一个变量，变量可以出现在任意位置，
+// 变量后面可以紧跟`:02`、`:01x`、`:04x`这样的格式说明，用来补零或者换成其他进制。
+// 各个部分之间不能有空格，否则不拼接
+fn paste_ident(
+    buf: &[proc_macro2::TokenTree],
+    idx: usize,
+    bindings: &[Binding],
+    shadowed: &[proc_macro2::Ident],
+) -> syn::Result<Option<(proc_macro2::Ident, usize)>> {
+    let first = match &buf[idx] {
+        proc_macro2::TokenTree::Ident(first) if !shadowed.contains(first) => first,
+        _ => return Ok(None),
+    };
+
+    let mut new_ident_litral = String::new();
+    let mut variable_span = None;
+    let mut pieces = 0;
+    let mut ident = first;
+    let mut last_span;
+    let mut stops_at_shadowed = false;
+    let mut j = idx;
+    loop {
+        let mut end = ident.span().end();
+        last_span = ident.span();
+        j += 1;
+        match bindings.iter().find(|b| b.ident == ident) {
+            Some(binding) => {
+                variable_span = Some(ident.span());
+                match parse_format_spec(buf, j, end)? {
+                    Some((spec, spec_end)) => {
+                        new_ident_litral.push_str(&format_with_spec(&binding.value, &spec, ident.span())?);
+                        end = spec_end;
+                        last_span = buf[j + 1].span();
+                        j += 2;
+                    }
+                    None => new_ident_litral.push_str(&binding.value.to_ident_fragment()),
+                }
+            }
+            None => new_ident_litral.push_str(&ident.to_string()),
+        }
+        pieces += 1;
+
+        // 校验是否连续，无空格
+        match (buf.get(j), buf.get(j + 1)) {
+            (Some(proc_macro2::TokenTree::Punct(p)), Some(proc_macro2::TokenTree::Ident(next)))
+                if (p.as_char() == '#' || p.as_char() == '~')
+                    && end == p.span().start()
+                    && p.span().end() == next.span().start() =>
+            {
+                // 嵌套的`seq!`自己的变量由内层拼接，在它前面停下来，把`~M`原样留给内层
+                if shadowed.contains(next) {
+                    stops_at_shadowed = true;
+                    break;
+                }
+                ident = next;
+                j += 1;
+            }
+            _ => break,
+        }
+    }
+
+    let variable_span = match variable_span {
+        Some(span) if pieces > 1 || stops_at_shadowed => span,
+        _ => return Ok(None),
+    };
+    // 负数、`-`、`²`这类字符都不能出现在标识符中，标识符也不能以数字开头。
+    // 按照Rust的词法规则检查一遍，否则`Ident::new`会直接panic
+    if syn::parse::Parser::parse_str(syn::Ident::parse_any, &new_ident_litral).is_err() {
+        return Err(syn::Error::new(
+            variable_span,
+            format!("pasting produces `{}`, which is not a valid identifier", new_ident_litral),
+        ));
+    }
+    // 内层的`seq!`要检查拼接结果和后面的`~`是否连续，所以这时使用最后一个部分的位置
+    let span = if stops_at_shadowed { last_span } else { first.span() };
+    Ok(Some((proc_macro2::Ident::new(&new_ident_litral, span), j - idx)))
+}
+
+// 拼接时变量后面的格式说明：补零的宽度和进制
+struct FormatSpec {
+    width: usize,
+    radix: char, // `d`表示十进制，其余和`format!`中的`x`、`X`、`o`、`b`含义相同
+}
+
+// 变量后面紧跟着`:`和一个以数字开头的字面量（例如`02`、`04x`）时，解析出格式说明，
+// 同时返回格式说明结束的位置。`N: u8`这种中间有空格的写法不是格式说明，
+// `v~N:X`这种紧跟着标识符的写法也不是，`X`可能是一个类型，原样保留
+fn parse_format_spec(
+    buf: &[proc_macro2::TokenTree],
+    j: usize,
+    end: proc_macro2::LineColumn,
+) -> syn::Result<Option<(FormatSpec, proc_macro2::LineColumn)>> {
+    let spec = match (buf.get(j), buf.get(j + 1)) {
+        (Some(proc_macro2::TokenTree::Punct(colon)), Some(spec @ proc_macro2::TokenTree::Literal(_)))
+            if colon.as_char() == ':' && end == colon.span().start() && colon.span().end() == spec.span().start() =>
+        {
+            spec
+        }
+        _ => return Ok(None),
+    };
+    let text = spec.to_string();
+    let (digits, radix) = match text.char_indices().last() {
+        Some((i, c @ ('x' | 'X' | 'o' | 'b'))) => (&text[..i], c),
+        _ => (text.as_str(), 'd'),
+    };
+    // 紧跟在变量后面的字面量一定是格式说明，写错了要报错
+    if digits.len() < 2 || !digits.starts_with('0') || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(syn::Error::new(
+            spec.span(),
+            format!("invalid format spec `{}`, expected a zero padded width such as `02`, optionally followed by `x`, `X`, `o` or `b`", text),
+        ));
+    }
+    let width = digits.parse().unwrap_or(0);
+    Ok(Some((FormatSpec { width, radix }, spec.span().end())))
+}
+
+// 按照格式说明输出变量的值，只有整数可以补零和换进制
+fn format_with_spec(value: &SeqValue, spec: &FormatSpec, span: proc_macro2::Span) -> syn::Result<String> {
+    let n = match *value {
+        SeqValue::Int(n) => n,
+        _ => return Err(syn::Error::new(span, "a format spec can only be applied to an integer variable")),
+    };
+    let sign = if n.is_negative() { "-" } else { "" };
+    let (width, magnitude) = (spec.width, n.magnitude());
+    let digits = match spec.radix {
+        'x' => format!("{:0width$x}", magnitude),
+        'X' => format!("{:0width$X}", magnitude),
+        'o' => format!("{:0width$o}", magnitude),
+        'b' => format!("{:0width$b}", magnitude),
+        _ => format!("{:0width$}", magnitude),
+    };
+    Ok(format!("{}{}", sign, digits))
+}
+
+// 生成替换变量用的整数字面量。负数是`-`和字面量两个Token，需要用圆括号括起来，
+// 这样`N.to_string()`这类表达式中负号仍然和数字结合在一起（不可见的分组在表达式中起不到这个作用）
+fn make_int_literal(n: Int, suffix: &str, span: proc_macro2::Span) -> proc_macro2::TokenStream {
+    let lit = syn::LitInt::new(&format!("{}{}", n.magnitude(), suffix), span);
+    if !n.is_negative() {
+        return quote::quote!(#lit);
     }
+    let mut minus = proc_macro2::Punct::new('-', proc_macro2::Spacing::Alone);
+    minus.set_span(span);
+    let mut group = proc_macro2::Group::new(proc_macro2::Delimiter::Parenthesis, quote::quote!(#minus #lit));
+    group.set_span(span);
+    quote::quote!(#group)
 }
@@ -0,0 +1,41 @@
+// Ranges may start or end at a negative number, and can be followed by a
+// `step K` (or `by K`) clause and by `rev`. The values are the same as
+// `(START..END).step_by(K).rev()` would produce.
+//
+// A negative value is substituted as `-` followed by the literal, wrapped in
+// parentheses so that method calls like `N.to_string()` still apply to the
+// negative number.
+// Negative values cannot be pasted into identifiers.
+
+use seq::seq;
+
+fn main() {
+    let mut values = Vec::new();
+    seq!(N in -4..4 step 2 {
+        values.push(N);
+    });
+    assert_eq!(values, [-4, -2, 0, 2]);
+
+    let mut values = Vec::new();
+    seq!(N in 0..=10 by 5 rev {
+        values.push(N);
+    });
+    assert_eq!(values, [10, 5, 0]);
+
+    let mut values = Vec::new();
+    seq!(N in -3..=-1 rev {
+        #(
+            values.push(N.to_string());
+        )*
+    });
+    assert_eq!(values, ["-1", "-2", "-3"]);
+
+    let x: i32 = -2;
+    let found = seq!(N in -3..3 {
+        match x {
+            #(N => Some(N),)*
+            _ => None,
+        }
+    });
+    assert_eq!(found, Some(-2));
+}
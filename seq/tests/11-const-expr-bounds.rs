@@ -0,0 +1,26 @@
+// Range bounds and steps may be written as constant expressions made of
+// integer literals and the operators `+ - * / % << >>`, with parentheses and
+// unary minus. They are evaluated inside the macro using Rust's operator
+// precedence, so `seq!(N in 0..(1 << 4) { ... })` and `0..16*2` work without
+// going through a `macro_rules!` indirection.
+
+use seq::seq;
+
+seq!(N in 0..(1 << 4) {
+    const REGS: [u32; 16] = [#(N * 4,)*];
+});
+
+seq!(N in 0..16*2 {
+    const WIDE: usize = 0 #(+ N)*;
+});
+
+fn main() {
+    assert_eq!(REGS[15], 60);
+    assert_eq!(WIDE, 31 * 32 / 2);
+
+    let mut values = Vec::new();
+    seq!(N in -(2 + 2)..=1 + 2 * 3 - 8 % 5 step 1 << 1 {
+        values.push(N);
+    });
+    assert_eq!(values, [-4, -2, 0, 2, 4]);
+}
@@ -0,0 +1,12 @@
+// The macro cannot see the value of constants, so range bounds may only use
+// literals. The error points at the offending operand.
+
+use seq::seq;
+
+const LEN: usize = 4;
+
+seq!(N in 0..LEN * 2 {
+    fn f~N() {}
+});
+
+fn main() {}
@@ -0,0 +1,41 @@
+// When the range bounds carry a type suffix, every generated literal keeps it,
+// so `seq!(N in 0u8..4u8 { ... })` produces `0u8`, `1u8`, ... and generated
+// constants have the intended type. Ranges cover the full `u128` and `i128`
+// domains, including their extreme values.
+
+use seq::seq;
+
+fn type_name_of<T>(_: T) -> &'static str {
+    std::any::type_name::<T>()
+}
+
+fn main() {
+    seq!(N in 0u8..4u8 {
+        let x = N;
+        assert_eq!(type_name_of(x), "u8");
+    });
+
+    let mut total = Vec::new();
+    seq!(N in 0..3usize {
+        #(total.push(type_name_of(N));)*
+    });
+    assert_eq!(total, ["usize", "usize", "usize"]);
+
+    let mut big = Vec::new();
+    seq!(N in 340282366920938463463374607431768211453u128..=340282366920938463463374607431768211455u128 {
+        big.push(N);
+    });
+    assert_eq!(big, [u128::MAX - 2, u128::MAX - 1, u128::MAX]);
+
+    let mut small = Vec::new();
+    seq!(N in -170141183460469231731687303715884105728i128..-170141183460469231731687303715884105726 {
+        small.push(N);
+    });
+    assert_eq!(small, [i128::MIN, i128::MIN + 1]);
+
+    let mut bytes = Vec::new();
+    seq!(N in 250u8..=255 step 2 {
+        bytes.push(N);
+    });
+    assert_eq!(bytes, [250, 252, 254]);
+}
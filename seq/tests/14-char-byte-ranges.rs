@@ -0,0 +1,36 @@
+// Besides integers, the range may be made of character literals or byte
+// literals. The variable substitutes as the char or byte literal itself, and
+// pasting it into an identifier appends the character, so `key_~C` with `C`
+// going over 'a'..='c' produces `key_a`, `key_b` and `key_c`.
+
+use seq::seq;
+
+seq!(C in 'a'..='c' {
+    #(
+        fn key_~C() -> char {
+            C
+        }
+    )*
+});
+
+seq!(B in b'0'..=b'9' step 3 {
+    const DIGITS: [u8; 4] = [#(B,)*];
+    #(
+        const DIGIT_~B: u8 = B;
+    )*
+});
+
+fn main() {
+    assert_eq!(key_a(), 'a');
+    assert_eq!(key_b(), 'b');
+    assert_eq!(key_c(), 'c');
+
+    assert_eq!(&DIGITS, b"0369");
+    assert_eq!([DIGIT_0, DIGIT_3, DIGIT_6, DIGIT_9], *b"0369");
+
+    let mut letters = String::new();
+    seq!(C in 'x'..'{' rev {
+        letters.push(C);
+    });
+    assert_eq!(letters, "zyx");
+}
@@ -0,0 +1,54 @@
+// Not every repetition is numeric. A bracketed list iterates over its items,
+// which may be types, paths or literals, and the variable is replaced by the
+// tokens of each item exactly as written. Items are parsed as types or as
+// expressions, so commas inside generic arguments such as `HashMap<u8, u16>`
+// or `HashMap<fn() -> u8, u16>` do not split items. Each item is substituted
+// as a whole, so `X * 2` with the item `1 + 2` is 6, like a macro_rules `$x:expr`.
+//
+// An `index I` clause binds a second variable that counts the repetitions
+// from 0, so it can be used next to the item, including in pasted identifiers.
+
+use seq::seq;
+use std::collections::HashMap;
+
+pub trait Width {
+    const BITS: u32;
+    const INDEX: usize;
+}
+
+seq!(T in [u8, u16, u32, u64] index N {
+    impl Width for T {
+        const BITS: u32 = T::BITS;
+        const INDEX: usize = N;
+    }
+});
+
+seq!(T in [Vec<u8>, HashMap<u8, u16>, std::string::String, HashMap<fn() -> u8, u16>] index N {
+    #(
+        fn make_~N() -> T {
+            <T>::new()
+        }
+    )*
+});
+
+seq!(NAME in ["alpha", "beta", "gamma",] index I rev {
+    const NAMES: [(usize, &str); 3] = [#((I, NAME),)*];
+});
+
+const DOUBLED: [i32; 2] = seq!(X in [1 + 2, 3] { [#(X * 2,)*] });
+const ABS: [i32; 2] = seq!(X in [-1i32, 2i32] { [#(X.abs(),)*] });
+
+fn main() {
+    assert_eq!(<u16 as Width>::BITS, 16);
+    assert_eq!(<u64 as Width>::INDEX, 3);
+
+    let _: Vec<u8> = make_0();
+    let _: HashMap<u8, u16> = make_1();
+    let _: String = make_2();
+    let _: HashMap<fn() -> u8, u16> = make_3();
+
+    assert_eq!(DOUBLED, [6, 6]);
+    assert_eq!(ABS, [1, 2]);
+
+    assert_eq!(NAMES, [(0, "gamma"), (1, "beta"), (2, "alpha")]);
+}
@@ -0,0 +1,87 @@
+// Several variables can be declared together, each with its own range:
+//
+//     seq!((I, J) in 0..2, 0..3 { ... })
+//
+// Without a `#(...)*` section the whole body is repeated over the cartesian
+// product of the ranges, the first variable changing slowest, as in nested
+// `for` loops.
+//
+// Repeat sections can be nested. The outermost `#(...)*` is driven by the
+// first variable, the section inside it by the second, and so on. This makes
+// it easy to generate two dimensional lookup tables. Identifiers inside
+// nested sections may paste several variables, e.g. `g~N~M`, and the outer
+// section leaves `~M` for the inner one.
+//
+// A `seq!` nested inside the body owns its own variable, even when it reuses
+// the name of an outer one, so the outer macro leaves it alone. Identifiers
+// in the nested body may paste both variables: the outer macro pastes up to
+// the inner variable and leaves `~M` for the inner macro, so `f~N~M` becomes
+// `f0~M` and then `f00`, `f01`, and so on.
+
+use seq::seq;
+
+pub struct Cell<const I: usize, const J: usize>;
+
+pub trait Value {
+    const VALUE: usize;
+}
+
+seq!((I, J) in 0..2, 0..3 {
+    impl Value for Cell<I, J> {
+        const VALUE: usize = I * 10 + J;
+    }
+});
+
+seq!((ROW, COL) in 0..3, 1..=3 {
+    const MUL: [[usize; 3]; 3] = [#( [ #( ROW * COL, )* ], )*];
+});
+
+seq!((A, B) in 0..2, 0..2 {
+    const PAIRS: [(u8, u8); 4] = [#((A, B),)*];
+});
+
+seq!(N in 0..3 {
+    const TOTAL: usize = 0 #(+ seq!(N in 0..4 { 0 #(+ N)* }))*;
+});
+
+seq!(N in 0..2 {
+    #(
+        seq!(M in 0..3 {
+            fn f~N~M() -> usize {
+                N * 10 + M
+            }
+        });
+    )*
+});
+
+seq!((N, M) in 0..2, 0..2 {
+    #(
+        #(
+            fn g~N~M() -> u32 {
+                N * 10 + M
+            }
+        )*
+    )*
+});
+
+seq!(N in 0..2 {
+    seq!(M in 0..2 {
+        const ID_~N:02~_~M: (usize, usize) = (N, M);
+    });
+});
+
+fn main() {
+    assert_eq!(<Cell<0, 0>>::VALUE, 0);
+    assert_eq!(<Cell<0, 2>>::VALUE, 2);
+    assert_eq!(<Cell<1, 0>>::VALUE, 10);
+    assert_eq!(<Cell<1, 2>>::VALUE, 12);
+
+    assert_eq!(MUL, [[0, 0, 0], [1, 2, 3], [2, 4, 6]]);
+    assert_eq!(PAIRS, [(0, 0), (0, 1), (1, 0), (1, 1)]);
+    assert_eq!(TOTAL, 18);
+
+    assert_eq!([f00(), f01(), f02()], [0, 1, 2]);
+    assert_eq!([f10(), f11(), f12()], [10, 11, 12]);
+    assert_eq!([g00(), g01(), g10(), g11()], [0, 1, 10, 11]);
+    assert_eq!([ID_00_0, ID_00_1, ID_01_0, ID_01_1], [(0, 0), (0, 1), (1, 0), (1, 1)]);
+}
@@ -0,0 +1,63 @@
+// The variable can appear anywhere in a pasted identifier, not only at the
+// end. Pieces are joined with `~` (or `#` on editions before 2021), and each
+// piece must touch its neighbours, without whitespace:
+//
+//     f~N~_impl    =>  f0_impl, f1_impl, ...
+//     C~_reg       =>  a_reg, b_reg, ...
+//
+// A format spec written right after the variable pads it with zeros, so the
+// generated names sort correctly, and can switch to another radix:
+//
+//     Reg~N:02     =>  Reg00, Reg01, ..., Reg31
+//     MASK~N:04b   =>  MASK0000, MASK0001, ...
+//     PAGE~N:01X   =>  PAGE0, ..., PAGEF
+//
+// The spec always starts with the zero padded width. An identifier after the
+// colon is not a spec, so `v~N:X` is still the parameter `v0` of type `X`.
+
+use seq::seq;
+
+seq!(N in 0..4 {
+    fn f~N~_impl() -> u32 {
+        N * 2
+    }
+});
+
+seq!(C in 'a'..='c' {
+    fn C~_reg() -> char {
+        C
+    }
+});
+
+seq!(N in 0..32 {
+    #[allow(dead_code)]
+    #[derive(Debug)]
+    enum Register {
+        #(Reg~N:02,)*
+    }
+});
+
+seq!(N in 0..16 step 5 {
+    const MASKS: [u8; 4] = [#(MASK~N:04b,)*];
+    #(const MASK~N:04b: u8 = N;)*
+    #(const PAGE~N:01X~_START: usize = N * 4096;)*
+});
+
+type X = u16;
+
+seq!(N in 0..2 {
+    #(fn double~N(v~N:X) -> X { v~N * 2 })*
+});
+
+fn main() {
+    assert_eq!(f0_impl() + f1_impl() + f2_impl() + f3_impl(), 12);
+    assert_eq!([a_reg(), b_reg(), c_reg()], ['a', 'b', 'c']);
+
+    assert_eq!(format!("{:?}", Register::Reg07), "Reg07");
+    assert_eq!(Register::Reg31 as u8, 31);
+
+    assert_eq!(MASKS, [MASK0000, MASK0101, MASK1010, MASK1111]);
+    assert_eq!(PAGEF_START, 15 * 4096);
+    assert_eq!(double0(3) + double1(4), 14);
+    assert_eq!(PAGEA_START - PAGE5_START, PAGE5_START - PAGE0_START);
+}
@@ -0,0 +1,15 @@
+// Pasting must produce a valid Rust identifier. Characters such as `²`, or
+// bytes above 0x7F, cannot appear in an identifier, and the error points at
+// the variable being pasted.
+
+use seq::seq;
+
+seq!(C in '²'..='²' {
+    fn f_~C() {}
+});
+
+seq!(B in b'\xbc'..=b'\xbc' {
+    fn g_~B() {}
+});
+
+fn main() {}
@@ -10,4 +10,13 @@ fn tests() {
     t.pass("tests/07-inclusive-range.rs");
     t.compile_fail("tests/08-ident-span.rs");
     t.pass("tests/09-interaction-with-macrorules.rs");
+    t.pass("tests/10-negative-step-rev.rs");
+    t.pass("tests/11-const-expr-bounds.rs");
+    t.compile_fail("tests/12-non-literal-bound.rs");
+    t.pass("tests/13-literal-suffix.rs");
+    t.pass("tests/14-char-byte-ranges.rs");
+    t.pass("tests/15-list.rs");
+    t.pass("tests/16-cartesian-nested.rs");
+    t.pass("tests/17-paste-suffix-padding.rs");
+    t.compile_fail("tests/18-invalid-pasted-ident.rs");
 }